@@ -8,8 +8,10 @@ const EXT_ID: &str = "adbacgifemdbhdkfppmeilbgppmhaobf";
 /// Entrypoint.
 fn main() -> Result<(), std::io::Error> {
     // Download the extension
-    let mut crx_query = ChromeCRXQuery::default();
-    crx_query.x = EXT_ID;
+    let crx_query = ChromeCRXQuery {
+        x: EXT_ID,
+        ..Default::default()
+    };
     let extension_crx = crx_query.download_blocking().unwrap();
 
     // Convert it to .zip
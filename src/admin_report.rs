@@ -0,0 +1,140 @@
+// Dependencies
+use std::io::ErrorKind;
+use crate::{batch::{BatchError, Stage}, is_extension_id, sink::Sink, verify_extension_id, ChromeCRXQuery, Error};
+
+/// An extension seen in a Google Admin console report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportedExtension {
+    pub id: String,
+    pub name: Option<String>,
+    /// How many browsers or devices it is installed on.
+    pub installs: Option<u64>,
+}
+impl ReportedExtension {
+    /// A query downloading this extension, with default parameters.
+    pub fn query(&self) -> ChromeCRXQuery<'_> {
+        ChromeCRXQuery {
+            x: &self.id,
+            ..Default::default()
+        }
+    }
+}
+
+/// Column names used for each field across report versions, lowercase.
+const ID_COLUMNS: [&str; 4] = ["app id", "extension id", "appid", "id"];
+const NAME_COLUMNS: [&str; 5] = ["app name", "extension name", "name", "display name", "displayname"];
+const INSTALL_COLUMNS: [&str; 5] = ["installs", "installation count", "install count", "browser device count", "browserdevicecount"];
+
+/// Splits CSV text into records, handling quoted fields.
+fn csv_records(data: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = data.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            ('"', _) => quoted = !quoted,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            },
+            ('\r', false) => {},
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Removes duplicate ids, keeping the first.
+fn dedup(mut extensions: Vec<ReportedExtension>) -> Vec<ReportedExtension> {
+    let mut seen = std::collections::HashSet::new();
+    extensions.retain(|x| seen.insert(x.id.clone()));
+    extensions
+}
+
+/// Parses the CSV export of the "Apps & extensions usage" report.
+///
+/// Rows without a valid extension id (e.g. Android apps) are skipped.
+pub fn parse_admin_report_csv(data: &str) -> Result<Vec<ReportedExtension>, Error> {
+    let mut records = csv_records(data).into_iter();
+    let header: Vec<String> = records
+        .next()
+        .unwrap_or_default()
+        .iter()
+        .map(|x| x.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|x| names.contains(&x.as_str()));
+    let id_column = column(&ID_COLUMNS)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "report has no extension id column"))?;
+    let (name_column, install_column) = (column(&NAME_COLUMNS), column(&INSTALL_COLUMNS));
+
+    let extensions = records
+        .filter_map(|record| {
+            let id = record.get(id_column)?.trim();
+            is_extension_id(id).then(|| ReportedExtension {
+                id: id.to_string(),
+                name: name_column.and_then(|x| record.get(x)).map(|x| x.trim().to_string()),
+                installs: install_column.and_then(|x| record.get(x)?.trim().parse().ok()),
+            })
+        })
+        .collect();
+    Ok(dedup(extensions))
+}
+
+/// Parses the JSON returned by the Chrome Management API's `countInstalledApps`,
+/// or a plain array of the same objects.
+pub fn parse_admin_report_json(data: &[u8]) -> Result<Vec<ReportedExtension>, Error> {
+    let report: serde_json::Value = serde_json::from_slice(data)?;
+    let apps = report["installedApps"]
+        .as_array()
+        .or(report.as_array())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "report has no installedApps"))?;
+
+    let extensions = apps
+        .iter()
+        .filter_map(|x| {
+            let id = x["appId"].as_str()?;
+            is_extension_id(id).then(|| ReportedExtension {
+                id: id.to_string(),
+                name: x["displayName"].as_str().map(str::to_string),
+                installs: x["browserDeviceCount"].as_u64()
+                    .or_else(|| x["browserDeviceCount"].as_str()?.parse().ok()),
+            })
+        })
+        .collect();
+    Ok(dedup(extensions))
+}
+
+/// Downloads every reported extension into `sink` as `<id>.crx`.
+///
+/// Failures do not stop the job, each extension's outcome is returned in order.
+pub fn download_reported(extensions: &[ReportedExtension], sink: &mut impl Sink) -> Vec<(String, Result<(), BatchError>)> {
+    extensions
+        .iter()
+        .map(|extension| {
+            let query = ChromeCRXQuery { verify_id: false, ..extension.query() };
+            let result = query
+                .download_blocking()
+                .map_err(|x| BatchError::new(&extension.id, Stage::Download, x))
+                .and_then(|crx| verify_extension_id(&crx, &extension.id)
+                    .map(|_| crx)
+                    .map_err(|x| BatchError::new(&extension.id, Stage::Verify, x))
+                )
+                .and_then(|crx| sink
+                    .put(&format!("{}.crx", extension.id), &crx)
+                    .map_err(|x| BatchError::new(&extension.id, Stage::Write, x))
+                );
+            (extension.id.clone(), result)
+        })
+        .collect()
+}
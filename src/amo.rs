@@ -0,0 +1,123 @@
+// Dependencies
+use std::io::ErrorKind;
+use serde_json::Value;
+use crate::{sha256_hex, Error};
+
+/// The root of the addons.mozilla.org API.
+pub const AMO_API_URL: &str = "https://addons.mozilla.org/api/v5";
+
+/// The latest file of an add-on, as listed by AMO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmoFile {
+    pub version: String,
+    /// Where the XPI can be downloaded from.
+    pub url: String,
+    /// Hex encoded, when AMO lists a SHA-256.
+    pub sha256: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// Downloads the latest XPI of a Firefox add-on from addons.mozilla.org.
+/// 
+/// XPIs are plain ZIPs, so the output works with everything that takes the output of [`crate::crx_to_zip`].
+#[derive(Clone)]
+pub struct FirefoxAMOQuery<'a> {
+    /// The API root, [`AMO_API_URL`] by default.
+    pub endpoint: &'a str,
+    /// The add-on's slug (`ublock-origin`), GUID (`uBlock0@raymondhill.net`) or numeric id.
+    pub addon: &'a str,
+    /// Overrides the `User-Agent` header, the reqwest default is used otherwise.
+    pub user_agent: Option<&'a str>,
+}
+impl<'a> FirefoxAMOQuery<'a> {
+    /// Creates a query for `addon`, against the public AMO.
+    pub fn new(addon: &'a str) -> Self {
+        Self { endpoint: AMO_API_URL, addon, user_agent: None }
+    }
+
+    /// The API url describing the add-on.
+    fn addon_url(&self) -> Result<reqwest::Url, Error> {
+        let mut url = reqwest::Url::parse(self.endpoint).map_err(Error::other)?;
+        url.path_segments_mut()
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid amo endpoint"))?
+            .pop_if_empty()
+            .extend(["addons", "addon", self.addon, ""]);
+        Ok(url)
+    }
+
+    /// Picks the current version's file out of the add-on details.
+    fn parse_file(details: &[u8]) -> Result<AmoFile, Error> {
+        let details: Value = serde_json::from_slice(details)?;
+        let version = &details["current_version"];
+        let file = &version["file"];
+        let url = file["url"]
+            .as_str()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "add-on has no downloadable file"))?;
+        Ok(AmoFile {
+            version: version["version"].as_str().unwrap_or_default().to_string(),
+            url: url.to_string(),
+            sha256: file["hash"].as_str().and_then(|x| x.strip_prefix("sha256:")).map(str::to_string),
+            size: file["size"].as_u64(),
+        })
+    }
+
+    /// Checks the XPI against the hash AMO listed.
+    fn check(file: &AmoFile, xpi: Vec<u8>) -> Result<Vec<u8>, Error> {
+        match &file.sha256 {
+            Some(sha256) if !sha256.eq_ignore_ascii_case(&sha256_hex(&xpi)) => {
+                Err(Error::new(ErrorKind::InvalidData, format!("xpi does not match the sha256 amo listed: {}", sha256)))
+            },
+            _ => Ok(xpi),
+        }
+    }
+
+    /// Looks up the add-on's latest file.
+    /// 
+    /// For a blocking version, use [`resolve_blocking`].
+    pub async fn resolve(&self) -> Result<AmoFile, Error> {
+        let mut request = crate::shared_client().get(self.addon_url()?);
+        if let Some(user_agent) = self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        let response = request.send().await.and_then(|x| x.error_for_status())?;
+        Self::parse_file(&response.bytes().await?)
+    }
+
+    /// Looks up the add-on's latest file.
+    /// 
+    /// For a async version, use [`resolve`].
+    pub fn resolve_blocking(&self) -> Result<AmoFile, Error> {
+        let mut request = crate::shared_blocking_client().get(self.addon_url()?);
+        if let Some(user_agent) = self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        let response = request.send().and_then(|x| x.error_for_status())?;
+        Self::parse_file(&response.bytes()?)
+    }
+
+    /// Downloads the add-on's latest XPI, checking it against the hash AMO lists.
+    /// 
+    /// For a blocking version, use [`download_blocking`].
+    pub async fn download(&self) -> Result<Vec<u8>, Error> {
+        let file = self.resolve().await?;
+        let mut request = crate::shared_client().get(&file.url);
+        if let Some(user_agent) = self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        let response = request.send().await.and_then(|x| x.error_for_status())?;
+        Self::check(&file, response.bytes().await?.to_vec())
+    }
+
+    /// Downloads the add-on's latest XPI, checking it against the hash AMO lists.
+    /// 
+    /// For a async version, use [`download`].
+    pub fn download_blocking(&self) -> Result<Vec<u8>, Error> {
+        let file = self.resolve_blocking()?;
+        let mut request = crate::shared_blocking_client().get(&file.url);
+        if let Some(user_agent) = self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        let response = request.send().and_then(|x| x.error_for_status())?;
+        Self::check(&file, response.bytes()?.to_vec())
+    }
+}
@@ -0,0 +1,133 @@
+// Dependencies
+use std::io::{Cursor, ErrorKind, Read};
+use crate::Error;
+
+// Modules
+mod corpus;
+pub use corpus::{analyze_corpus, CorpusReport, Outlier, SharedFile, MIN_SHARED_FILE_SIZE, OUTLIER_THRESHOLD};
+mod endpoints;
+pub use endpoints::{endpoints, EndpointReport, HostReport};
+mod entropy;
+pub use entropy::{high_entropy_files, shannon_entropy, EntropyFinding, DATA_ENTROPY_THRESHOLD, MIN_ENTROPY_SIZE, SCRIPT_ENTROPY_THRESHOLD};
+mod export;
+pub use export::{write_csv, ExtensionRecord, CSV_COLUMNS};
+mod hashes;
+pub use hashes::{FileHashMismatch, HashEntry, HashManifest};
+mod kind;
+pub use kind::{kind, ExtensionKind};
+mod platform;
+pub use platform::{check_architecture, for_architecture, is_platform_app, nacl_modules, platform_specific_dirs, ArchMismatch, PLATFORM_SPECIFIC_DIR};
+mod sdks;
+pub use sdks::{sdks, SdkCategory, SdkFingerprint, SdkMatch, SDK_FINGERPRINTS};
+mod sourcemaps;
+pub use sourcemaps::{reconstruct_sources, source_maps, sources_from_map, SourceMapReference, SourceMapReport};
+mod wasm;
+pub use wasm::{wasm, WasmModule, WasmReport};
+
+/// The default limit on the inflated size of a [`Package`], which guards against ZIP bombs.
+pub const DEFAULT_MAX_PACKAGE_SIZE: u64 = 1024 * 1024 * 1024;
+/// How much of the size an entry claims is allocated up front, since the size in a ZIP can't be trusted.
+const MAX_PREALLOC: u64 = 1024 * 1024;
+
+/// A file inside of an extension package.
+#[derive(Debug, Clone)]
+pub struct PackageFile {
+    /// The path within the package, `/` separated.
+    pub path: String,
+    pub data: Vec<u8>,
+}
+impl PackageFile {
+    /// Whether the file has any of the given extensions (without the dot), case insensitive.
+    pub fn has_extension(&self, extensions: &[&str]) -> bool {
+        self.path
+            .rsplit_once('.')
+            .is_some_and(|(_, x)| extensions.iter().any(|y| x.eq_ignore_ascii_case(y)))
+    }
+
+    /// Whether the file is a script.
+    pub fn is_script(&self) -> bool {
+        self.has_extension(&["js", "mjs", "cjs"])
+    }
+}
+
+/// An unpacked extension, loaded into memory for analysis.
+#[derive(Debug, Clone, Default)]
+pub struct Package {
+    pub files: Vec<PackageFile>,
+}
+impl Package {
+    /// Loads every file from a zip, such as the output of [`crate::crx_to_zip`].
+    /// 
+    /// Fails once more than [`DEFAULT_MAX_PACKAGE_SIZE`] bytes are inflated, see [`Package::from_zip_with_limit`].
+    pub fn from_zip(zip: &[u8]) -> Result<Self, Error> {
+        Self::from_zip_with_limit(zip, DEFAULT_MAX_PACKAGE_SIZE)
+    }
+
+    /// Loads every file from a zip, failing once more than `max_size` bytes are inflated in total.
+    pub fn from_zip_with_limit(zip: &[u8], max_size: u64) -> Result<Self, Error> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(zip))?;
+        let mut files = Vec::with_capacity(archive.len());
+        let mut remaining = max_size;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            if entry.is_dir() {
+                continue;
+            }
+
+            // Read one byte past the limit, to tell hitting it apart from ending right at it
+            let mut data = Vec::with_capacity(entry.size().min(MAX_PREALLOC).min(remaining) as usize);
+            let read = (&mut entry).take(remaining.saturating_add(1)).read_to_end(&mut data)? as u64;
+            if read > remaining {
+                return Err(Error::new(ErrorKind::InvalidData, format!("package is larger than {} bytes once inflated", max_size)));
+            }
+            remaining -= read;
+            files.push(PackageFile { path: entry.name().to_string(), data });
+        }
+        Ok(Self { files })
+    }
+
+    /// Grabs a file by its path.
+    pub fn file(&self, path: &str) -> Option<&PackageFile> {
+        self.files.iter().find(|x| x.path == path)
+    }
+
+    /// Grabs `manifest.json`.
+    pub fn manifest(&self) -> Option<&PackageFile> {
+        self.file("manifest.json")
+    }
+}
+
+/// The combined output of every analysis pass.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisReport {
+    pub endpoints: EndpointReport,
+    pub sdks: Vec<SdkMatch>,
+    pub high_entropy: Vec<EntropyFinding>,
+    pub source_maps: SourceMapReport,
+    pub wasm: WasmReport,
+}
+
+/// Runs every analysis pass over the package.
+pub fn analyze(package: &Package) -> AnalysisReport {
+    AnalysisReport {
+        endpoints: endpoints(package),
+        sdks: sdks(package),
+        high_entropy: high_entropy_files(package),
+        source_maps: source_maps(package),
+        wasm: wasm(package),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::zip_with_manifest;
+    use super::*;
+
+    #[test]
+    fn from_zip_limits_inflated_size() {
+        let manifest = "x".repeat(100);
+        let zip = zip_with_manifest(&manifest);
+        assert_eq!(Package::from_zip_with_limit(&zip, 100).unwrap().files[0].data, manifest.as_bytes());
+        assert!(Package::from_zip_with_limit(&zip, 99).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+// Dependencies
+use std::collections::{BTreeMap, BTreeSet};
+use crate::sha256_hex;
+use super::{analyze, ExtensionRecord, Package};
+
+/// Files smaller than this are not considered for [`CorpusReport::shared_files`], since tiny files are identical by chance.
+pub const MIN_SHARED_FILE_SIZE: usize = 1024;
+/// How many standard deviations from the mean an extension needs to be to count as an outlier.
+pub const OUTLIER_THRESHOLD: f64 = 3.0;
+
+/// A file with the same contents in more than one extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedFile {
+    /// Hex encoded.
+    pub sha256: String,
+    pub size: usize,
+    /// `(extension id, path)` of every copy.
+    pub copies: BTreeSet<(String, String)>,
+}
+
+/// An extension far outside the rest of the corpus on some metric.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outlier {
+    pub id: String,
+    /// The metric, e.g. `total_size` or `file_count`.
+    pub metric: &'static str,
+    pub value: f64,
+    /// How many standard deviations from the mean it is.
+    pub z_score: f64,
+}
+
+/// Aggregates over many extensions.
+#[derive(Debug, Clone, Default)]
+pub struct CorpusReport {
+    /// Every extension analyzed, see [`super::write_csv`].
+    pub records: Vec<ExtensionRecord>,
+    /// How many extensions request each permission, most common first.
+    pub permissions: Vec<(String, usize)>,
+    /// How many extensions bundle each SDK, most common first.
+    pub sdks: Vec<(String, usize)>,
+    /// Identical files found under different extension ids, a sign of clones or shared code.
+    pub shared_files: Vec<SharedFile>,
+    pub outliers: Vec<Outlier>,
+}
+
+/// Sorts counts most common first, then by name.
+fn ranked(counts: BTreeMap<String, usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// Finds the extensions that are more than [`OUTLIER_THRESHOLD`] standard deviations from the mean.
+fn outliers(records: &[ExtensionRecord], metric: &'static str, value: impl Fn(&ExtensionRecord) -> f64) -> Vec<Outlier> {
+    let values: Vec<f64> = records.iter().map(&value).collect();
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let deviation = (values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / values.len() as f64).sqrt();
+    if deviation == 0.0 {
+        return Vec::new();
+    }
+
+    records
+        .iter()
+        .zip(values)
+        .map(|(record, value)| Outlier { id: record.id.clone(), metric, value, z_score: (value - mean) / deviation })
+        .filter(|x| x.z_score.abs() > OUTLIER_THRESHOLD)
+        .collect()
+}
+
+/// Runs every analysis pass over each `(extension id, package)`, then aggregates the results.
+pub fn analyze_corpus<'a>(packages: impl IntoIterator<Item = (&'a str, &'a Package)>) -> CorpusReport {
+    let mut report = CorpusReport::default();
+    let mut permissions = BTreeMap::new();
+    let mut sdks = BTreeMap::new();
+    let mut files: BTreeMap<String, SharedFile> = BTreeMap::new();
+
+    for (id, package) in packages {
+        let record = ExtensionRecord::new(id, package, &analyze(package));
+        for permission in record.permissions.iter().chain(&record.host_permissions).collect::<BTreeSet<_>>() {
+            *permissions.entry(permission.clone()).or_default() += 1;
+        }
+        for sdk in &record.sdks {
+            *sdks.entry(sdk.clone()).or_default() += 1;
+        }
+        for file in package.files.iter().filter(|x| x.data.len() >= MIN_SHARED_FILE_SIZE) {
+            let sha256 = sha256_hex(&file.data);
+            files
+                .entry(sha256.clone())
+                .or_insert_with(|| SharedFile { sha256, size: file.data.len(), copies: BTreeSet::new() })
+                .copies
+                .insert((id.to_string(), file.path.clone()));
+        }
+        report.records.push(record);
+    }
+
+    // Only keep files shared between different ids
+    report.shared_files = files
+        .into_values()
+        .filter(|x| x.copies.iter().map(|(id, _)| id).collect::<BTreeSet<_>>().len() > 1)
+        .collect();
+    report.permissions = ranked(permissions);
+    report.sdks = ranked(sdks);
+    report.outliers = [
+        outliers(&report.records, "total_size", |x| x.total_size as f64),
+        outliers(&report.records, "file_count", |x| x.file_count as f64),
+    ].concat();
+
+    // Done
+    report
+}
@@ -0,0 +1,70 @@
+// Dependencies
+use std::collections::{BTreeMap, BTreeSet};
+use super::Package;
+
+/// URL schemes that are looked for. `*://` covers match patterns in the manifest.
+const SCHEMES: [&str; 5] = ["https://", "http://", "wss://", "ws://", "*://"];
+
+/// Everything referencing a single host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostReport {
+    /// Every distinct url seen for this host.
+    pub urls: BTreeSet<String>,
+    /// The files the urls were found in.
+    pub files: BTreeSet<String>,
+}
+
+/// The absolute urls referenced by a package, grouped by host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EndpointReport {
+    pub hosts: BTreeMap<String, HostReport>,
+}
+
+/// Extracts every absolute url referenced in the manifest and scripts of a package.
+pub fn endpoints(package: &Package) -> EndpointReport {
+    let mut report = EndpointReport::default();
+    for file in package.files.iter().filter(|x| x.path == "manifest.json" || x.is_script()) {
+        for url in find_urls(&String::from_utf8_lossy(&file.data)) {
+            let Some(host) = host_of(url) else {
+                continue;
+            };
+
+            let entry = report.hosts.entry(host).or_default();
+            entry.urls.insert(url.to_string());
+            entry.files.insert(file.path.clone());
+        }
+    }
+    report
+}
+
+/// Finds the absolute urls within some text.
+fn find_urls(text: &str) -> Vec<&str> {
+    let mut urls = Vec::new();
+    let mut rest = text;
+    while let Some((start, scheme)) = SCHEMES
+        .iter()
+        .filter_map(|x| rest.find(x).map(|i| (i, x)))
+        .min_by_key(|x| x.0)
+    {
+        let tail = &rest[start..];
+        let end = tail[scheme.len()..]
+            .find(|x: char| x.is_whitespace() || "\"'`<>()[]{}\\|^".contains(x))
+            .map_or(tail.len(), |x| x + scheme.len());
+        urls.push(&tail[..end]);
+        rest = &tail[end..];
+    }
+    urls
+}
+
+/// Grabs the lowercase host of a url, if it looks like a real one (not a template).
+fn host_of(url: &str) -> Option<String> {
+    let authority = url.split_once("://")?.1;
+    let authority = authority
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default().trim_end_matches('.');
+    let valid = !host.is_empty() && host.chars().all(|x| x.is_ascii_alphanumeric() || "-.*_".contains(x));
+    valid.then(|| host.to_ascii_lowercase())
+}
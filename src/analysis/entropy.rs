@@ -0,0 +1,56 @@
+// Dependencies
+use super::Package;
+
+/// Scripts above this many bits per byte are likely packed or encoded, minified code sits around 5.
+pub const SCRIPT_ENTROPY_THRESHOLD: f64 = 5.8;
+/// Other files above this many bits per byte are likely compressed or encrypted.
+pub const DATA_ENTROPY_THRESHOLD: f64 = 7.5;
+/// Files smaller than this are skipped, their entropy is too noisy to mean anything.
+pub const MIN_ENTROPY_SIZE: usize = 1024;
+
+/// Formats that are compressed by design, so a high entropy is expected.
+const COMPRESSED_EXTENSIONS: [&str; 14] = [
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "ico", "woff", "woff2", "mp3", "ogg", "mp4", "webm", "zip",
+];
+
+/// A file with an unusually high entropy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntropyFinding {
+    pub path: String,
+    /// In bits per byte, from 0 to 8.
+    pub entropy: f64,
+    pub size: usize,
+}
+
+/// The Shannon entropy of `data` in bits per byte.
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts = [0usize; 256];
+    for byte in data {
+        counts[*byte as usize] += 1;
+    }
+    counts
+        .iter()
+        .filter(|x| **x > 0)
+        .map(|x| {
+            let p = *x as f64 / data.len() as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Flags scripts and data files with an unusually high entropy, which are often packed or encrypted payloads.
+pub fn high_entropy_files(package: &Package) -> Vec<EntropyFinding> {
+    package.files
+        .iter()
+        .filter(|x| x.data.len() >= MIN_ENTROPY_SIZE && !x.has_extension(&COMPRESSED_EXTENSIONS))
+        .filter_map(|x| {
+            let entropy = shannon_entropy(&x.data);
+            let threshold = if x.is_script() { SCRIPT_ENTROPY_THRESHOLD } else { DATA_ENTROPY_THRESHOLD };
+            (entropy > threshold).then(|| EntropyFinding { path: x.path.clone(), entropy, size: x.data.len() })
+        })
+        .collect()
+}
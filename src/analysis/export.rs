@@ -0,0 +1,119 @@
+// Dependencies
+use std::io::Write;
+use serde_json::Value;
+use crate::Error;
+use super::{AnalysisReport, Package};
+
+/// The columns written by [`write_csv`], in order.
+/// List columns are `;` separated.
+pub const CSV_COLUMNS: [&str; 16] = [
+    "id", "name", "version", "manifest_version", "permissions", "host_permissions",
+    "file_count", "total_size", "hosts", "sdks", "high_entropy_files", "source_maps",
+    "wasm_modules", "csp_allows_wasm", "has_background", "has_content_scripts",
+];
+
+/// One extension's package and analysis, flattened into a single row.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtensionRecord {
+    pub id: String,
+    /// As written in the manifest, so possibly a `__MSG_name__` placeholder.
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub manifest_version: Option<u64>,
+    pub permissions: Vec<String>,
+    /// `host_permissions`, plus any url patterns in `permissions` for manifest v2.
+    pub host_permissions: Vec<String>,
+    pub file_count: usize,
+    /// The uncompressed size of every file.
+    pub total_size: u64,
+    /// Hosts referenced by the code, see [`super::endpoints`].
+    pub hosts: Vec<String>,
+    pub sdks: Vec<String>,
+    pub high_entropy_files: usize,
+    pub source_maps: usize,
+    pub wasm_modules: usize,
+    pub csp_allows_wasm: bool,
+    pub has_background: bool,
+    pub has_content_scripts: bool,
+}
+impl ExtensionRecord {
+    /// Flattens a package and its analysis.
+    pub fn new(id: impl Into<String>, package: &Package, report: &AnalysisReport) -> Self {
+        let manifest: Value = package
+            .manifest()
+            .and_then(|x| serde_json::from_slice(&x.data).ok())
+            .unwrap_or_default();
+        let strings = |key: &str| -> Vec<String> {
+            manifest[key]
+                .as_array()
+                .map(|x| x.iter().filter_map(|x| x.as_str()).map(str::to_string).collect())
+                .unwrap_or_default()
+        };
+
+        // Manifest v2 mixes url patterns into the permissions
+        let is_host = |x: &String| x.contains("://") || x == "<all_urls>";
+        let (mut host_permissions, permissions): (Vec<_>, Vec<_>) = strings("permissions").into_iter().partition(is_host);
+        host_permissions.extend(strings("host_permissions"));
+
+        Self {
+            id: id.into(),
+            name: manifest["name"].as_str().map(str::to_string),
+            version: manifest["version"].as_str().map(str::to_string),
+            manifest_version: manifest["manifest_version"].as_u64(),
+            permissions,
+            host_permissions,
+            file_count: package.files.len(),
+            total_size: package.files.iter().map(|x| x.data.len() as u64).sum(),
+            hosts: report.endpoints.hosts.keys().cloned().collect(),
+            sdks: report.sdks.iter().map(|x| x.name.to_string()).collect(),
+            high_entropy_files: report.high_entropy.len(),
+            source_maps: report.source_maps.files.len() + report.source_maps.references.len(),
+            wasm_modules: report.wasm.modules.len(),
+            csp_allows_wasm: report.wasm.csp_allows_wasm,
+            has_background: !manifest["background"].is_null(),
+            has_content_scripts: manifest["content_scripts"].as_array().is_some_and(|x| !x.is_empty()),
+        }
+    }
+
+    /// The row, in the order of [`CSV_COLUMNS`].
+    pub fn to_row(&self) -> Vec<String> {
+        let optional = |x: &Option<String>| x.clone().unwrap_or_default();
+        vec![
+            self.id.clone(),
+            optional(&self.name),
+            optional(&self.version),
+            self.manifest_version.map(|x| x.to_string()).unwrap_or_default(),
+            self.permissions.join(";"),
+            self.host_permissions.join(";"),
+            self.file_count.to_string(),
+            self.total_size.to_string(),
+            self.hosts.join(";"),
+            self.sdks.join(";"),
+            self.high_entropy_files.to_string(),
+            self.source_maps.to_string(),
+            self.wasm_modules.to_string(),
+            self.csp_allows_wasm.to_string(),
+            self.has_background.to_string(),
+            self.has_content_scripts.to_string(),
+        ]
+    }
+}
+
+/// Quotes a CSV field if needed.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes the records as CSV, with a header row of [`CSV_COLUMNS`].
+pub fn write_csv<'a>(records: impl IntoIterator<Item = &'a ExtensionRecord>, mut writer: impl Write) -> Result<(), Error> {
+    writeln!(writer, "{}", CSV_COLUMNS.join(","))?;
+    for record in records {
+        let row: Vec<String> = record.to_row().iter().map(|x| csv_field(x)).collect();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    Ok(writer.flush()?)
+}
@@ -0,0 +1,123 @@
+// Dependencies
+use std::{fmt, io::ErrorKind, str::FromStr};
+use crate::{sha256_hex, Error};
+use super::Package;
+
+/// The hash and size of a single file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashEntry {
+    pub path: String,
+    /// Hex encoded.
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// A file that no longer matches a [`HashManifest`].
+/// 
+/// Not to be confused with [`crate::updatecheck::HashMismatch`], which is about a whole CRX.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileHashMismatch {
+    /// The file is in the manifest, but not the package.
+    Missing(String),
+    /// The file is in the package, but not the manifest.
+    Added(String),
+    /// The file's contents changed.
+    Modified(String),
+}
+
+/// The SHA-256 and size of every file in a package.
+///
+/// Serializes to one `<sha256>  <size>  <path>` line per file, sorted by path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HashManifest {
+    pub entries: Vec<HashEntry>,
+}
+impl HashManifest {
+    /// Hashes every file in the package.
+    pub fn from_package(package: &Package) -> Self {
+        let mut entries: Vec<_> = package.files
+            .iter()
+            .map(|x| HashEntry { path: x.path.clone(), sha256: sha256_hex(&x.data), size: x.data.len() as u64 })
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Self { entries }
+    }
+
+    /// Compares the manifest against a package, returning every file that differs.
+    pub fn verify(&self, package: &Package) -> Vec<FileHashMismatch> {
+        let current = Self::from_package(package);
+        let mut mismatches = Vec::new();
+        for entry in &self.entries {
+            match current.entries.iter().find(|x| x.path == entry.path) {
+                None => mismatches.push(FileHashMismatch::Missing(entry.path.clone())),
+                Some(x) if x != entry => mismatches.push(FileHashMismatch::Modified(entry.path.clone())),
+                Some(_) => {}
+            }
+        }
+        for entry in current.entries.iter().filter(|x| !self.entries.iter().any(|y| y.path == x.path)) {
+            mismatches.push(FileHashMismatch::Added(entry.path.clone()));
+        }
+        mismatches
+    }
+}
+impl fmt::Display for HashManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}  {}  {}", entry.sha256, entry.size, entry.path)?;
+        }
+        Ok(())
+    }
+}
+impl FromStr for HashManifest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let entries = s.lines()
+            .filter(|x| !x.is_empty())
+            .map(|line| {
+                let mut parts = line.splitn(3, "  ");
+                let (Some(sha256), Some(size), Some(path)) = (parts.next(), parts.next(), parts.next()) else {
+                    return Err(Error::new(ErrorKind::InvalidData, "malformed hash manifest line"));
+                };
+                let size = size.parse().map_err(|_| Error::new(ErrorKind::InvalidData, "malformed hash manifest size"))?;
+                Ok(HashEntry { path: path.to_string(), sha256: sha256.to_string(), size })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::PackageFile;
+
+    fn package(files: &[(&str, &[u8])]) -> Package {
+        Package { files: files.iter().map(|(path, data)| PackageFile { path: path.to_string(), data: data.to_vec() }).collect() }
+    }
+
+    #[test]
+    fn hashes_every_file_sorted() {
+        let manifest = HashManifest::from_package(&package(&[("js/b.js", b"b"), ("a.json", b"")]));
+        assert_eq!(manifest.to_string(), concat!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  0  a.json\n",
+            "3e23e8160039594a33894f6564e1b1348bbd7a0088d42c4acb73eeaed59c009d  1  js/b.js\n",
+        ));
+        assert_eq!(manifest.to_string().parse::<HashManifest>().unwrap(), manifest);
+        assert!("00  not a size  a.json".parse::<HashManifest>().is_err());
+        assert!("00  1".parse::<HashManifest>().is_err());
+    }
+
+    #[test]
+    fn verify_reports_each_change() {
+        let manifest = HashManifest::from_package(&package(&[("a.js", b"a"), ("b.js", b"b"), ("c.js", b"c")]));
+        assert_eq!(manifest.verify(&package(&[("c.js", b"c"), ("a.js", b"a"), ("b.js", b"b")])), []);
+
+        let changed = package(&[("a.js", b"a"), ("b.js", b"B"), ("d.js", b"d")]);
+        assert_eq!(manifest.verify(&changed), [
+            FileHashMismatch::Modified("b.js".into()),
+            FileHashMismatch::Missing("c.js".into()),
+            FileHashMismatch::Added("d.js".into()),
+        ]);
+    }
+}
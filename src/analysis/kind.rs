@@ -0,0 +1,91 @@
+// Dependencies
+use super::Package;
+
+/// What kind of package a CRX holds, which the update server serves all the same.
+/// 
+/// No other `acceptformat` or parameters are needed to download themes or apps, and they convert like any other CRX.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ExtensionKind {
+    Extension,
+    /// Only colours and images, there is nothing to run.
+    Theme,
+    /// A legacy app that is just a link to a website, with no files beyond icons.
+    HostedApp,
+    /// A legacy app that opens a page from the package in a tab.
+    LegacyPackagedApp,
+    /// A Chrome App with a background page, see [`super::is_platform_app`].
+    PlatformApp,
+    /// Code shared by other extensions, declared with `export`.
+    SharedModule,
+}
+impl ExtensionKind {
+    /// Works out the kind from `manifest.json`, the same way Chrome does.
+    pub fn from_manifest(manifest: &serde_json::Value) -> Self {
+        let app = &manifest["app"];
+        if manifest["theme"].is_object() {
+            Self::Theme
+        } else if manifest["export"].is_object() {
+            Self::SharedModule
+        } else if app["background"].is_object() {
+            Self::PlatformApp
+        } else if app["launch"]["web_url"].is_string() || app["urls"].is_array() {
+            Self::HostedApp
+        } else if app["launch"]["local_path"].is_string() {
+            Self::LegacyPackagedApp
+        } else {
+            Self::Extension
+        }
+    }
+
+    /// Whether the package can contain code that runs, which themes and hosted apps can't.
+    pub fn has_scripts(&self) -> bool {
+        !matches!(self, Self::Theme | Self::HostedApp)
+    }
+}
+
+/// The kind of the package, or `None` if it has no readable manifest.
+pub fn kind(package: &Package) -> Option<ExtensionKind> {
+    let manifest = package.manifest()?;
+    let manifest = serde_json::from_slice(&manifest.data).ok()?;
+    Some(ExtensionKind::from_manifest(&manifest))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::{archive::read_manifest, crx_to_zip, mock::crx3, tests::{crx2, zip_with_manifest}};
+    use super::*;
+
+    #[test]
+    fn classifies_manifests() {
+        let cases = [
+            (json!({"name": "a", "version": "1", "theme": {"colors": {"frame": [0, 0, 0]}}}), ExtensionKind::Theme),
+            (json!({"name": "a", "version": "1", "app": {"urls": ["https://example.com/"], "launch": {"web_url": "https://example.com/"}}}), ExtensionKind::HostedApp),
+            (json!({"name": "a", "version": "1", "app": {"launch": {"local_path": "main.html"}}}), ExtensionKind::LegacyPackagedApp),
+            (json!({"name": "a", "version": "1", "app": {"background": {"scripts": ["main.js"]}}}), ExtensionKind::PlatformApp),
+            (json!({"name": "a", "version": "1", "export": {"whitelist": []}}), ExtensionKind::SharedModule),
+            (json!({"name": "a", "version": "1", "background": {"service_worker": "main.js"}}), ExtensionKind::Extension),
+        ];
+        for (manifest, kind) in cases {
+            assert_eq!(ExtensionKind::from_manifest(&manifest), kind, "{}", manifest);
+        }
+    }
+
+    #[test]
+    fn themes_and_apps_convert() {
+        let manifests = [
+            r#"{"name":"a","version":"1","theme":{"colors":{"frame":[0,0,0]}}}"#,
+            r#"{"name":"a","version":"1","app":{"launch":{"local_path":"main.html"}}}"#,
+            r#"{"name":"a","version":"1","app":{"background":{"scripts":["main.js"]}}}"#,
+        ];
+        for manifest in manifests {
+            let expected: serde_json::Value = serde_json::from_str(manifest).unwrap();
+            for crx in [crx2(&zip_with_manifest(manifest)), crx3(&zip_with_manifest(manifest))] {
+                let zip = crx_to_zip(crx.clone(), None).unwrap();
+                assert_eq!(read_manifest(&zip).unwrap(), expected);
+                assert_eq!(read_manifest(&crx).unwrap(), expected);
+            }
+        }
+    }
+}
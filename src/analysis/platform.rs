@@ -0,0 +1,114 @@
+// Dependencies
+use crate::Architecture;
+use super::{Package, PackageFile};
+
+/// The directory holding per-architecture files, e.g. `_platform_specific/x86-64/`.
+pub const PLATFORM_SPECIFIC_DIR: &str = "_platform_specific/";
+
+/// Whether the package is a (legacy) Chrome App rather than an extension.
+pub fn is_platform_app(package: &Package) -> bool {
+    package.manifest()
+        .and_then(|x| serde_json::from_slice::<serde_json::Value>(&x.data).ok())
+        .is_some_and(|x| x["app"].is_object())
+}
+
+/// The architectures the package ships platform specific files for, e.g. `x86-64`.
+pub fn platform_specific_dirs(package: &Package) -> Vec<String> {
+    let mut dirs: Vec<String> = package.files
+        .iter()
+        .filter_map(|x| x.path.strip_prefix(PLATFORM_SPECIFIC_DIR)?.split_once('/'))
+        .map(|x| x.0.to_string())
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+/// Lays the package out the way Chrome installs it on `arch`.
+///
+/// The contents of `_platform_specific/<arch>/` are moved to the root, and other architectures are dropped.
+pub fn for_architecture(package: &Package, arch: &Architecture) -> Package {
+    let prefix = format!("{}{}/", PLATFORM_SPECIFIC_DIR, arch);
+    let mut files: Vec<PackageFile> = package.files
+        .iter()
+        .filter(|x| !x.path.starts_with(PLATFORM_SPECIFIC_DIR))
+        .cloned()
+        .collect();
+    for file in &package.files {
+        let Some(path) = file.path.strip_prefix(&prefix) else {
+            continue;
+        };
+        files.retain(|x| x.path != path);
+        files.push(PackageFile { path: path.to_string(), data: file.data.clone() });
+    }
+    Package { files }
+}
+
+/// Extracts the NaCl (`.nexe`) modules for `arch`, along with the portable PNaCl (`.pexe`) modules
+/// and the `.nmf` manifests describing them.
+///
+/// Paths are the ones the modules get once installed, see [`for_architecture`].
+pub fn nacl_modules(package: &Package, arch: &Architecture) -> Vec<PackageFile> {
+    for_architecture(package, arch)
+        .files
+        .into_iter()
+        .filter(|x| x.has_extension(&["nexe", "pexe", "nmf"]))
+        .collect()
+}
+
+/// A platform specific binary, or declaration, that does not match the requested architecture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchMismatch {
+    /// The offending file, or `manifest.json` for declarations.
+    pub path: String,
+    /// What was found instead, e.g. `arm` or `x86-64, x86-32`.
+    pub found: String,
+}
+
+/// The architecture an ELF binary (such as a `.nexe`) was built for, if it is one we know.
+fn elf_architecture(data: &[u8]) -> Option<&'static str> {
+    if !data.starts_with(b"\x7fELF") {
+        return None;
+    }
+    let machine = u16::from_le_bytes(data.get(18..20)?.try_into().unwrap());
+    match machine {
+        0x03 => Some("x86-32"),
+        0x28 => Some("arm"),
+        0x3e => Some("x86-64"),
+        0x08 => Some("mips32"),
+        _ => None,
+    }
+}
+
+/// Checks that the platform specific binaries of a package match `arch`, catching silently wrong platform downloads.
+/// 
+/// Looks at the `_platform_specific` directories, the `platforms` declared in the manifest,
+/// and the ELF header of every NaCl module that would be installed. An empty result means nothing looked wrong.
+pub fn check_architecture(package: &Package, arch: &Architecture) -> Vec<ArchMismatch> {
+    let arch_name = arch.to_string();
+    let mut mismatches = Vec::new();
+
+    // Shipped directories
+    let dirs = platform_specific_dirs(package);
+    if !dirs.is_empty() && !dirs.contains(&arch_name) {
+        mismatches.push(ArchMismatch { path: PLATFORM_SPECIFIC_DIR.to_string(), found: dirs.join(", ") });
+    }
+
+    // Declared sub packages
+    let manifest = package.manifest().and_then(|x| serde_json::from_slice::<serde_json::Value>(&x.data).ok());
+    if let Some(platforms) = manifest.as_ref().and_then(|x| x["platforms"].as_array()) {
+        let declared: Vec<&str> = platforms.iter().filter_map(|x| x["nacl_arch"].as_str()).collect();
+        if !declared.is_empty() && !declared.contains(&arch_name.as_str()) {
+            mismatches.push(ArchMismatch { path: "manifest.json".to_string(), found: declared.join(", ") });
+        }
+    }
+
+    // The binaries themselves
+    for module in for_architecture(package, arch).files.iter().filter(|x| x.has_extension(&["nexe"])) {
+        if let Some(found) = elf_architecture(&module.data).filter(|x| *x != arch_name) {
+            mismatches.push(ArchMismatch { path: module.path.clone(), found: found.to_string() });
+        }
+    }
+
+    mismatches
+}
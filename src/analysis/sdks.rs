@@ -0,0 +1,74 @@
+// Dependencies
+use std::collections::BTreeSet;
+use super::Package;
+
+/// What a third-party SDK is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum SdkCategory {
+    Analytics,
+    ErrorTracking,
+    SessionRecording,
+    Advertising,
+    Monetization,
+}
+
+/// The strings that give away an embedded SDK.
+#[derive(Debug, Clone, Copy)]
+pub struct SdkFingerprint {
+    pub name: &'static str,
+    pub category: SdkCategory,
+    /// Matched case sensitively against scripts and pages, any one is enough.
+    pub patterns: &'static [&'static str],
+}
+
+/// The SDKs [`sdks`] looks for.
+pub const SDK_FINGERPRINTS: &[SdkFingerprint] = &[
+    SdkFingerprint { name: "Google Analytics", category: SdkCategory::Analytics, patterns: &["google-analytics.com/analytics.js", "google-analytics.com/collect", "google-analytics.com/mp/collect", "googletagmanager.com/gtag/js", "GoogleAnalyticsObject"] },
+    SdkFingerprint { name: "Google Tag Manager", category: SdkCategory::Analytics, patterns: &["googletagmanager.com/gtm.js"] },
+    SdkFingerprint { name: "Firebase Analytics", category: SdkCategory::Analytics, patterns: &["firebase-analytics", "firebaselogging.googleapis.com"] },
+    SdkFingerprint { name: "Mixpanel", category: SdkCategory::Analytics, patterns: &["api.mixpanel.com", "api-js.mixpanel.com", "mixpanel.init("] },
+    SdkFingerprint { name: "Amplitude", category: SdkCategory::Analytics, patterns: &["api.amplitude.com", "api2.amplitude.com", "amplitude.getInstance("] },
+    SdkFingerprint { name: "Segment", category: SdkCategory::Analytics, patterns: &["api.segment.io", "cdn.segment.com"] },
+    SdkFingerprint { name: "PostHog", category: SdkCategory::Analytics, patterns: &["posthog.init(", "app.posthog.com", "i.posthog.com"] },
+    SdkFingerprint { name: "Heap", category: SdkCategory::Analytics, patterns: &["heapanalytics.com"] },
+    SdkFingerprint { name: "Yandex Metrica", category: SdkCategory::Analytics, patterns: &["mc.yandex.ru/metrika", "mc.yandex.ru/watch"] },
+    SdkFingerprint { name: "Sentry", category: SdkCategory::ErrorTracking, patterns: &["ingest.sentry.io", "Sentry.init("] },
+    SdkFingerprint { name: "Bugsnag", category: SdkCategory::ErrorTracking, patterns: &["notify.bugsnag.com", "sessions.bugsnag.com"] },
+    SdkFingerprint { name: "Hotjar", category: SdkCategory::SessionRecording, patterns: &["static.hotjar.com", "script.hotjar.com"] },
+    SdkFingerprint { name: "LogRocket", category: SdkCategory::SessionRecording, patterns: &["cdn.logrocket.io", "r.lr-ingest.io"] },
+    SdkFingerprint { name: "Facebook Pixel", category: SdkCategory::Advertising, patterns: &["connect.facebook.net", "facebook.com/tr?"] },
+    SdkFingerprint { name: "ExtensionPay", category: SdkCategory::Monetization, patterns: &["extensionpay.com"] },
+    SdkFingerprint { name: "Mellowtel", category: SdkCategory::Monetization, patterns: &["mellowtel"] },
+    SdkFingerprint { name: "Infatica", category: SdkCategory::Monetization, patterns: &["infatica.io"] },
+    SdkFingerprint { name: "Bright Data", category: SdkCategory::Monetization, patterns: &["luminati.io", "brightdata.com"] },
+];
+
+/// An SDK found in a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdkMatch {
+    pub name: &'static str,
+    pub category: SdkCategory,
+    /// The files a fingerprint was found in.
+    pub files: BTreeSet<String>,
+}
+
+/// Detects the analytics, tracking and monetization SDKs embedded in a package.
+pub fn sdks(package: &Package) -> Vec<SdkMatch> {
+    let sources: Vec<_> = package.files
+        .iter()
+        .filter(|x| x.is_script() || x.has_extension(&["html", "htm"]))
+        .map(|x| (&x.path, String::from_utf8_lossy(&x.data)))
+        .collect();
+
+    SDK_FINGERPRINTS
+        .iter()
+        .filter_map(|sdk| {
+            let files: BTreeSet<String> = sources
+                .iter()
+                .filter(|(_, text)| sdk.patterns.iter().any(|x| text.contains(x)))
+                .map(|(path, _)| path.to_string())
+                .collect();
+            (!files.is_empty()).then_some(SdkMatch { name: sdk.name, category: sdk.category, files })
+        })
+        .collect()
+}
@@ -0,0 +1,231 @@
+// Dependencies
+use std::io::ErrorKind;
+use base64::{engine::general_purpose, Engine as _};
+use crate::Error;
+use super::{Package, PackageFile};
+
+/// The comment marker pointing a script at its source map.
+const SOURCE_MAPPING_URL: &str = "sourceMappingURL=";
+/// The prefix of source maps inlined into the script.
+const INLINE_PREFIX: &str = "data:application/json;base64,";
+
+/// A `sourceMappingURL` comment in a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapReference {
+    /// The script containing the comment.
+    pub script: String,
+    /// The url as written, inline maps are shortened to their `data:` prefix.
+    pub url: String,
+    /// The path of the map within the package, if it is shipped with it.
+    pub resolved: Option<String>,
+    /// Whether the map is inlined into the script as a `data:` url.
+    pub inline: bool,
+}
+
+/// The source maps shipped with, or referenced by, a package.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMapReport {
+    /// Every `.map` file.
+    pub files: Vec<String>,
+    pub references: Vec<SourceMapReference>,
+}
+
+/// Finds the `sourceMappingURL` comments within a script.
+fn find_references(script: &str) -> impl Iterator<Item = &str> {
+    script
+        .match_indices(SOURCE_MAPPING_URL)
+        .filter(|(i, _)| {
+            let before = script[..*i].trim_end_matches(['#', '@', ' ']);
+            before.ends_with("//") || before.ends_with("/*")
+        })
+        .map(|(i, _)| {
+            let rest = &script[i + SOURCE_MAPPING_URL.len()..];
+            let end = rest.find(|x: char| x.is_whitespace() || x == '*').unwrap_or(rest.len());
+            &rest[..end]
+        })
+        .filter(|x| !x.is_empty())
+}
+
+/// Resolves `url` relative to the file at `base`, within the package.
+fn resolve(base: &str, url: &str) -> Option<String> {
+    if url.contains("://") || url.starts_with("data:") {
+        return None;
+    }
+
+    let mut parts: Vec<&str> = if url.starts_with('/') {
+        Vec::new()
+    } else {
+        base.split('/').collect()
+    };
+    parts.pop();
+    for part in url.split(['?', '#']).next().unwrap_or_default().split('/') {
+        match part {
+            "" | "." => {},
+            ".." => { parts.pop(); },
+            x => parts.push(x),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Detects `.map` files and `sourceMappingURL` comments.
+pub fn source_maps(package: &Package) -> SourceMapReport {
+    let files = package.files
+        .iter()
+        .filter(|x| x.has_extension(&["map"]))
+        .map(|x| x.path.clone())
+        .collect();
+
+    let mut references = Vec::new();
+    for script in package.files.iter().filter(|x| x.is_script() || x.has_extension(&["css"])) {
+        for url in find_references(&String::from_utf8_lossy(&script.data)) {
+            let inline = url.starts_with("data:");
+            references.push(SourceMapReference {
+                script: script.path.clone(),
+                url: if inline { url.split(',').next().unwrap_or(url).to_string() } else { url.to_string() },
+                resolved: resolve(&script.path, url).filter(|x| package.file(x).is_some()),
+                inline,
+            });
+        }
+    }
+
+    SourceMapReport { files, references }
+}
+
+/// Turns a source path from a map into a relative path, e.g. `webpack:///./src/a.ts` into `src/a.ts`.
+fn clean_source_path(path: &str) -> String {
+    let path = path.split_once("://").map_or(path, |x| x.1);
+    path.split('/')
+        .filter(|x| !x.is_empty() && *x != "." && *x != "..")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Prepends `root` to `source`, adding the `/` between them if it is missing.
+fn join_source_root(root: &str, source: &str) -> String {
+    match root.is_empty() || root.ends_with('/') {
+        true => format!("{}{}", root, source),
+        false => format!("{}/{}", root, source),
+    }
+}
+
+/// Reconstructs the original sources embedded (`sourcesContent`) in a single source map.
+pub fn sources_from_map(map: &[u8]) -> Result<Vec<PackageFile>, Error> {
+    let map: serde_json::Value = serde_json::from_slice(map)?;
+    let root = map["sourceRoot"].as_str().unwrap_or_default();
+    let (Some(sources), Some(contents)) = (map["sources"].as_array(), map["sourcesContent"].as_array()) else {
+        return Err(Error::new(ErrorKind::InvalidData, "source map does not embed its sources"));
+    };
+
+    Ok(
+        sources
+            .iter()
+            .zip(contents)
+            .filter_map(|(source, content)| Some(PackageFile {
+                path: clean_source_path(&join_source_root(root, source.as_str()?)),
+                data: content.as_str()?.as_bytes().to_vec(),
+            }))
+            .collect()
+    )
+}
+
+/// Reconstructs the original sources from every source map in a package, including inline ones.
+///
+/// Maps that do not embed their sources are skipped.
+pub fn reconstruct_sources(package: &Package) -> Vec<PackageFile> {
+    let mut maps: Vec<Vec<u8>> = package.files
+        .iter()
+        .filter(|x| x.has_extension(&["map"]))
+        .map(|x| x.data.clone())
+        .collect();
+    for script in package.files.iter().filter(|x| x.is_script() || x.has_extension(&["css"])) {
+        let text = String::from_utf8_lossy(&script.data);
+        maps.extend(
+            find_references(&text)
+                .filter_map(|x| x.strip_prefix(INLINE_PREFIX))
+                .filter_map(|x| general_purpose::STANDARD.decode(x).ok())
+        );
+    }
+
+    let mut sources: Vec<PackageFile> = Vec::new();
+    for file in maps.iter().filter_map(|x| sources_from_map(x).ok()).flatten() {
+        if !sources.iter().any(|x| x.path == file.path) {
+            sources.push(file);
+        }
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_relative_to_script() {
+        let cases = [
+            ("js/app.js", "app.js.map", Some("js/app.js.map")),
+            ("js/app.js", "./maps/app.map", Some("js/maps/app.map")),
+            ("js/app.js", "../maps/app.map", Some("maps/app.map")),
+            ("js/app.js", "../../../app.map", Some("app.map")),
+            ("js/app.js", "/maps/app.map", Some("maps/app.map")),
+            ("app.js", "app.js.map?v=2#x", Some("app.js.map")),
+            ("app.js", "https://cdn.example.com/app.js.map", None),
+            ("app.js", "data:application/json;base64,e30=", None),
+        ];
+        for (base, url, expected) in cases {
+            assert_eq!(resolve(base, url).as_deref(), expected, "{} {}", base, url);
+        }
+    }
+
+    #[test]
+    fn cleans_source_paths() {
+        let cases = [
+            ("webpack:///./src/a.ts", "src/a.ts"),
+            ("webpack://my-ext/../src/a.ts", "my-ext/src/a.ts"),
+            ("../../src//a.ts", "src/a.ts"),
+            ("/abs/a.ts", "abs/a.ts"),
+            ("a.ts", "a.ts"),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(clean_source_path(path), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn joins_source_root() {
+        let cases = [
+            ("", "a.ts", "a.ts"),
+            ("src/", "a.ts", "src/a.ts"),
+            ("src", "a.ts", "src/a.ts"),
+            ("webpack:///", "./lib/a.ts", "lib/a.ts"),
+        ];
+        for (root, source, expected) in cases {
+            let map = serde_json::json!({ "sourceRoot": root, "sources": [source], "sourcesContent": ["x"] });
+            let files = sources_from_map(map.to_string().as_bytes()).unwrap();
+            assert_eq!(files[0].path, expected, "{} {}", root, source);
+        }
+        assert!(sources_from_map(br#"{"sources":["a.ts"]}"#).is_err());
+    }
+
+    #[test]
+    fn finds_inline_and_shipped_maps() {
+        let map = br#"{"sources":["src/a.ts","src/b.ts"],"sourcesContent":["let a = 1;",null]}"#;
+        let inline = format!("f();\n//# sourceMappingURL={}{}\n", INLINE_PREFIX, general_purpose::STANDARD.encode(map));
+        let package = Package { files: vec![
+            PackageFile { path: "js/inline.js".into(), data: inline.into_bytes() },
+            PackageFile { path: "js/app.js".into(), data: b"g();\n/*# sourceMappingURL=app.js.map */".to_vec() },
+            PackageFile { path: "js/app.js.map".into(), data: br#"{"sourceRoot":"src","sources":["c.ts"],"sourcesContent":["let c;"]}"#.to_vec() },
+        ] };
+
+        let report = source_maps(&package);
+        assert_eq!(report.files, ["js/app.js.map"]);
+        assert_eq!(report.references, [
+            SourceMapReference { script: "js/inline.js".into(), url: "data:application/json;base64".into(), resolved: None, inline: true },
+            SourceMapReference { script: "js/app.js".into(), url: "app.js.map".into(), resolved: Some("js/app.js.map".into()), inline: false },
+        ]);
+
+        // Sources without content are skipped
+        let sources: Vec<_> = reconstruct_sources(&package).into_iter().map(|x| (x.path, x.data)).collect();
+        assert_eq!(sources, [("src/c.ts".to_string(), b"let c;".to_vec()), ("src/a.ts".to_string(), b"let a = 1;".to_vec())]);
+    }
+}
@@ -0,0 +1,215 @@
+// Dependencies
+use super::Package;
+
+/// The magic number and version every WASM module starts with.
+const WASM_MAGIC: [u8; 4] = *b"\0asm";
+
+/// A WASM module found in a package.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WasmModule {
+    pub path: String,
+    pub size: usize,
+    /// Imports as `module.name`.
+    pub imports: Vec<String>,
+    pub exports: Vec<String>,
+    /// Whether the sections could not be fully parsed, in which case the summaries may be incomplete.
+    pub malformed: bool,
+}
+
+/// WASM usage within a package.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WasmReport {
+    pub modules: Vec<WasmModule>,
+    /// Whether the manifest's content security policy allows compiling WASM (`wasm-unsafe-eval`).
+    pub csp_allows_wasm: bool,
+}
+
+/// A cursor over the binary format.
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let (first, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(*first)
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.0.len() {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Some(head)
+    }
+
+    /// An unsigned LEB128 integer.
+    fn leb(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for i in 0..10 {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let len = self.leb()? as usize;
+        Some(String::from_utf8_lossy(self.bytes(len)?).to_string())
+    }
+
+    /// Table and memory limits.
+    fn limits(&mut self) -> Option<()> {
+        let flags = self.byte()?;
+        self.leb()?;
+        if flags & 1 != 0 {
+            self.leb()?;
+        }
+        Some(())
+    }
+}
+
+/// Reads the imports of an import section.
+fn parse_imports(mut section: Reader) -> Option<Vec<String>> {
+    let count = section.leb()?;
+    let mut imports = Vec::new();
+    for _ in 0..count {
+        let module = section.name()?;
+        let name = section.name()?;
+        match section.byte()? {
+            0 => { section.leb()?; },
+            1 => { section.byte()?; section.limits()?; },
+            2 => section.limits()?,
+            3 => { section.bytes(2)?; },
+            4 => { section.byte()?; section.leb()?; },
+            _ => return None,
+        }
+        imports.push(format!("{}.{}", module, name));
+    }
+    Some(imports)
+}
+
+/// Reads the exports of an export section.
+fn parse_exports(mut section: Reader) -> Option<Vec<String>> {
+    let count = section.leb()?;
+    let mut exports = Vec::new();
+    for _ in 0..count {
+        exports.push(section.name()?);
+        section.byte()?;
+        section.leb()?;
+    }
+    Some(exports)
+}
+
+/// Summarises the imports and exports of a module.
+fn parse_module(path: &str, data: &[u8]) -> WasmModule {
+    let mut module = WasmModule { path: path.to_string(), size: data.len(), ..Default::default() };
+    let mut reader = Reader(data.get(8..).unwrap_or_default());
+    while !reader.0.is_empty() {
+        let section = reader.byte().zip(reader.leb()).and_then(|(id, len)| Some((id, reader.bytes(len as usize)?)));
+        let parsed = match section {
+            Some((2, x)) => parse_imports(Reader(x)).map(|x| module.imports = x),
+            Some((7, x)) => parse_exports(Reader(x)).map(|x| module.exports = x),
+            Some(_) => Some(()),
+            None => None,
+        };
+        if parsed.is_none() {
+            module.malformed = true;
+            break;
+        }
+    }
+    module
+}
+
+/// Reports the WASM modules in a package, and whether the manifest allows running them.
+pub fn wasm(package: &Package) -> WasmReport {
+    let modules = package.files
+        .iter()
+        .filter(|x| x.has_extension(&["wasm"]) || x.data.starts_with(&WASM_MAGIC))
+        .map(|x| parse_module(&x.path, &x.data))
+        .collect();
+
+    let csp_allows_wasm = package.manifest()
+        .and_then(|x| serde_json::from_slice::<serde_json::Value>(&x.data).ok())
+        .is_some_and(|x| {
+            let csp = x["content_security_policy"].to_string();
+            csp.contains("wasm-unsafe-eval") || csp.contains("wasm-eval")
+        });
+
+    WasmReport { modules, csp_allows_wasm }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::PackageFile;
+
+    /// A module importing `env.log` and exporting `run`, which does nothing.
+    fn module() -> Vec<u8> {
+        [
+            &b"\0asm\x01\0\0\0"[..],
+            // Types, a single `() -> ()`
+            &[0x01, 0x04, 0x01, 0x60, 0x00, 0x00],
+            // Imports, `env.log` as function 0
+            &[0x02, 0x0b, 0x01, 0x03, b'e', b'n', b'v', 0x03, b'l', b'o', b'g', 0x00, 0x00],
+            // Functions and exports, `run` as function 1
+            &[0x03, 0x02, 0x01, 0x00],
+            &[0x07, 0x07, 0x01, 0x03, b'r', b'u', b'n', 0x00, 0x01],
+            // Code, an empty body
+            &[0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b],
+        ].concat()
+    }
+
+    #[test]
+    fn reads_leb128() {
+        let cases: [(&[u8], Option<u64>); 7] = [
+            (&[0x00], Some(0)),
+            (&[0x7f], Some(127)),
+            (&[0x80, 0x01], Some(128)),
+            (&[0xe5, 0x8e, 0x26], Some(624485)),
+            (&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01], Some(u64::MAX)),
+            (&[0x80], None),
+            (&[0x80; 11], None),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(Reader(bytes).leb(), expected, "{:02x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn parses_imports_and_exports() {
+        let module = parse_module("a.wasm", &module());
+        assert_eq!(module.imports, ["env.log"]);
+        assert_eq!(module.exports, ["run"]);
+        assert!(!module.malformed);
+    }
+
+    #[test]
+    fn flags_truncated_modules() {
+        let data = module();
+
+        // Within the code section, after the imports and exports were read
+        let module = parse_module("a.wasm", &data[..data.len() - 2]);
+        assert_eq!((module.imports.len(), module.exports.len()), (1, 1));
+        assert!(module.malformed);
+
+        // Within the import section
+        let module = parse_module("a.wasm", &data[..20]);
+        assert!(module.imports.is_empty() && module.exports.is_empty());
+        assert!(module.malformed);
+    }
+
+    #[test]
+    fn reports_modules_and_csp() {
+        let package = Package { files: vec![
+            PackageFile { path: "manifest.json".into(), data: br#"{"content_security_policy":{"extension_pages":"script-src 'self' 'wasm-unsafe-eval'"}}"#.to_vec() },
+            PackageFile { path: "lib/engine.bin".into(), data: module() },
+            PackageFile { path: "lib/app.js".into(), data: b"run()".to_vec() },
+        ] };
+        let report = wasm(&package);
+        assert_eq!(report.modules.iter().map(|x| x.path.as_str()).collect::<Vec<_>>(), ["lib/engine.bin"]);
+        assert!(report.csp_allows_wasm);
+    }
+}
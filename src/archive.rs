@@ -0,0 +1,116 @@
+// Dependencies
+use std::io::{ErrorKind, Read};
+use crate::{crx_to_zip_slice, Error};
+
+/// The signature of the end of central directory record.
+const EOCD_SIGNATURE: [u8; 4] = [b'P', b'K', 5, 6];
+/// The size of the end of central directory record, excluding the comment.
+const EOCD_LEN: usize = 22;
+
+/// Where a zip archive ends, and how many bytes follow it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrailingData {
+    /// The length of the zip archive itself, including its comment.
+    pub zip_len: usize,
+    /// The amount of bytes appended after the archive.
+    pub trailing_len: usize,
+}
+
+/// An entry of a zip archive, as recorded in its central directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    /// The path of the entry, as stored in the archive.
+    pub path: String,
+    /// The uncompressed size.
+    pub size: u64,
+    pub compressed_size: u64,
+    /// The CRC-32 of the uncompressed data.
+    pub crc: u32,
+}
+
+/// Locates the end of central directory record.
+///
+/// Returns its offset, ignoring candidates whose comment would run past the end of the data,
+/// or whose central directory does not end right before it.
+fn find_eocd(zip: &[u8]) -> Option<usize> {
+    let mut end = zip.len();
+    while end >= EOCD_LEN {
+        let offset = zip[..end].windows(4).rposition(|x| x == EOCD_SIGNATURE)?;
+        let record = &zip[offset..];
+        if record.len() >= EOCD_LEN {
+            let cd_size = u32::from_le_bytes(record[12..16].try_into().unwrap());
+            let cd_offset = u32::from_le_bytes(record[16..20].try_into().unwrap());
+            let comment_len = u16::from_le_bytes(record[20..22].try_into().unwrap()) as usize;
+
+            // Zip64 archives store the real values elsewhere
+            let zip64 = cd_offset == u32::MAX || cd_size == u32::MAX;
+            let cd_matches = zip64 || (cd_offset as usize).checked_add(cd_size as usize) == Some(offset);
+            if cd_matches && offset + EOCD_LEN + comment_len <= zip.len() {
+                return Some(offset);
+            }
+        }
+        end = offset + 3;
+    }
+    None
+}
+
+/// Detects bytes appended after the end of a zip archive (padding, signing artifacts, smuggled data).
+pub fn find_trailing_data(zip: &[u8]) -> Result<TrailingData, Error> {
+    let eocd = find_eocd(zip)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "could not find end of central directory"))?;
+    let comment_len = u16::from_le_bytes([zip[eocd + 20], zip[eocd + 21]]) as usize;
+    let zip_len = eocd + EOCD_LEN + comment_len;
+    Ok(TrailingData { zip_len, trailing_len: zip.len() - zip_len })
+}
+
+/// Removes any bytes appended after the end of a zip archive.
+///
+/// Returns the removed bytes, so they can be preserved elsewhere.
+pub fn strip_trailing_data(zip: &mut Vec<u8>) -> Result<Vec<u8>, Error> {
+    let trailing = find_trailing_data(zip)?;
+    Ok(zip.split_off(trailing.zip_len))
+}
+
+/// Walks the central directory and reads every entry, checking its CRC-32.
+///
+/// Fails on the first corrupt entry, naming it, so a bad archive is caught before extraction.
+pub fn validate_zip(zip: &[u8]) -> Result<(), Error> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(zip))
+        .map_err(|x| Error::new(ErrorKind::InvalidData, x))?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|x| Error::new(ErrorKind::InvalidData, x))?;
+        std::io::copy(&mut entry, &mut std::io::sink())
+            .map_err(|x| Error::new(ErrorKind::InvalidData, format!("zip entry {} is corrupt: {}", entry.name(), x)))?;
+    }
+    Ok(())
+}
+
+/// Reads `manifest.json` out of a CRX or ZIP, inflating only that entry.
+/// 
+/// The CRX is not copied, see [`crx_to_zip_slice`]. A leading UTF-8 BOM is ignored, as Chrome does.
+pub fn read_manifest(crx_or_zip: &[u8]) -> Result<serde_json::Value, Error> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(crx_to_zip_slice(crx_or_zip)?))
+        .map_err(|x| Error::new(ErrorKind::InvalidData, x))?;
+    let mut manifest = Vec::new();
+    archive.by_name("manifest.json")?.read_to_end(&mut manifest)?;
+    Ok(serde_json::from_slice(manifest.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&manifest))?)
+}
+
+/// Lists the entries of a CRX or ZIP, without inflating any of them.
+/// 
+/// The CRX is not copied, see [`crx_to_zip_slice`].
+pub fn list_entries(crx_or_zip: &[u8]) -> Result<Vec<EntryInfo>, Error> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(crx_to_zip_slice(crx_or_zip)?))
+        .map_err(|x| Error::new(ErrorKind::InvalidData, x))?;
+    (0..archive.len())
+        .map(|i| {
+            let entry = archive.by_index_raw(i).map_err(|x| Error::new(ErrorKind::InvalidData, x))?;
+            Ok(EntryInfo {
+                path: entry.name().to_string(),
+                size: entry.size(),
+                compressed_size: entry.compressed_size(),
+                crc: entry.crc32(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,68 @@
+// Dependencies
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use serde_json::json;
+use crate::Error;
+
+/// A single request, and what came of it.
+#[derive(Debug, Clone, Default)]
+pub struct AuditEntry {
+    pub method: String,
+    pub url: String,
+    /// Where the response came from, after following redirects.
+    pub final_url: Option<String>,
+    pub status: Option<u16>,
+    /// The size of the response body.
+    pub bytes: usize,
+    pub duration: Duration,
+    /// The hex encoded SHA-256 of the response body.
+    pub sha256: Option<String>,
+    /// Why the request failed, if it did.
+    pub error: Option<String>,
+}
+
+/// Appends one JSON line per request to a file, for environments that must account for everything fetched.
+/// 
+/// The file is opened, in append mode, on the first entry.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+}
+impl AuditLog {
+    /// Creates a log appending to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), file: Mutex::new(None) }
+    }
+
+    /// Appends an entry, stamped with the current time.
+    pub fn record(&self, entry: &AuditEntry) -> Result<(), Error> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut line = json!({
+            "timestamp": timestamp.as_secs_f64(),
+            "method": entry.method,
+            "url": entry.url,
+            "final_url": entry.final_url,
+            "status": entry.status,
+            "bytes": entry.bytes,
+            "duration_ms": entry.duration.as_millis() as u64,
+            "sha256": entry.sha256,
+            "error": entry.error,
+        })
+        .to_string();
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap();
+        if file.is_none() {
+            *file = Some(OpenOptions::new().create(true).append(true).open(&self.path)?);
+        }
+        let file = file.as_mut().unwrap();
+        file.write_all(line.as_bytes())?;
+        Ok(file.flush()?)
+    }
+}
@@ -0,0 +1,47 @@
+// Dependencies
+use std::fmt;
+use crate::Error;
+
+/// A step of the download pipeline, for telling the operator what to retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum Stage {
+    #[strum(serialize="update-check")]
+    UpdateCheck,
+    #[strum(serialize="download")]
+    Download,
+    #[strum(serialize="verify")]
+    Verify,
+    #[strum(serialize="write")]
+    Write,
+}
+
+/// A failure within a batch operation, with the extension and stage it happened at.
+/// 
+/// Displays as a single line, e.g. `abc...: download failed: connection reset`.
+#[derive(Debug)]
+pub struct BatchError {
+    pub id: String,
+    pub stage: Stage,
+    pub source: Error,
+}
+impl BatchError {
+    /// Wraps `source` with the extension id and stage.
+    pub fn new(id: impl Into<String>, stage: Stage, source: impl Into<Error>) -> Self {
+        Self { id: id.into(), stage, source: source.into() }
+    }
+}
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} failed: {}", self.id, self.stage, self.source)
+    }
+}
+impl std::error::Error for BatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+impl From<BatchError> for Error {
+    fn from(value: BatchError) -> Self {
+        Error::new(value.source.kind(), value)
+    }
+}
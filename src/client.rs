@@ -0,0 +1,21 @@
+// Dependencies
+use std::sync::OnceLock;
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+static BLOCKING_CLIENT: OnceLock<reqwest::blocking::Client> = OnceLock::new();
+
+/// The async client shared by every query in the process, built on first use.
+/// 
+/// Reusing it keeps connections and TLS sessions alive between downloads.
+/// Its connections belong to whichever runtime opened them, so programs juggling several runtimes
+/// (e.g. one per test) are better off with their own client, see [`crate::ChromeCRXQuery::download_with_client`].
+pub fn shared_client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+/// The blocking client shared by every query in the process, built on first use.
+/// 
+/// Reusing it keeps connections and TLS sessions alive between downloads.
+pub fn shared_blocking_client() -> &'static reqwest::blocking::Client {
+    BLOCKING_CLIENT.get_or_init(reqwest::blocking::Client::new)
+}
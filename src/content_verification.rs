@@ -0,0 +1,110 @@
+// Dependencies
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use crate::analysis::{Package, PackageFile};
+
+/// Where Chrome keeps its content verification data within an extension.
+pub const COMPUTED_HASHES_PATH: &str = "_metadata/computed_hashes.json";
+/// The size of each hashed block, Chrome only supports 4096.
+pub const BLOCK_SIZE: usize = 4096;
+
+/// Hashes `data` in [`BLOCK_SIZE`] blocks. Empty data still gets a single block.
+pub fn block_hashes(data: &[u8]) -> Vec<Vec<u8>> {
+    if data.is_empty() {
+        return vec![Sha256::digest([]).to_vec()];
+    }
+    data.chunks(BLOCK_SIZE)
+        .map(|x| Sha256::digest(x).to_vec())
+        .collect()
+}
+
+/// Computes the Merkle tree root of some block hashes, as stored in `verified_contents.json`.
+///
+/// Each node hashes up to `BLOCK_SIZE / 32` children, the same as Chrome's `ComputeTreeHashRoot`.
+pub fn tree_hash_root(hashes: &[Vec<u8>]) -> Vec<u8> {
+    let branch_factor = BLOCK_SIZE / 32;
+    let mut level = hashes.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(branch_factor)
+            .map(|x| Sha256::digest(x.concat()).to_vec())
+            .collect();
+    }
+    level.pop().unwrap_or_else(|| Sha256::digest([]).to_vec())
+}
+
+/// Generates a Chrome compatible `computed_hashes.json` for every file in the package.
+pub fn computed_hashes(package: &Package) -> String {
+    let file_hashes: Vec<_> = package.files
+        .iter()
+        .filter(|x| !x.path.starts_with("_metadata/"))
+        .map(|x| json!({
+            "block_hashes": block_hashes(&x.data)
+                .iter()
+                .map(|x| general_purpose::STANDARD.encode(x))
+                .collect::<Vec<_>>(),
+            "block_size": BLOCK_SIZE,
+            "path": x.path,
+        }))
+        .collect();
+    json!({ "file_hashes": file_hashes, "version": 2 }).to_string()
+}
+
+/// Adds (or replaces) [`COMPUTED_HASHES_PATH`] in the package, for use before repacking it.
+pub fn add_computed_hashes(package: &mut Package) {
+    let data = computed_hashes(package).into_bytes();
+    package.files.retain(|x| x.path != COMPUTED_HASHES_PATH);
+    package.files.push(PackageFile { path: COMPUTED_HASHES_PATH.to_string(), data });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_blocks() {
+        let data = vec![7u8; BLOCK_SIZE + 1];
+        assert_eq!(block_hashes(&[]), [Sha256::digest([]).to_vec()]);
+        assert_eq!(block_hashes(&data[..BLOCK_SIZE]), [Sha256::digest(&data[..BLOCK_SIZE]).to_vec()]);
+        assert_eq!(block_hashes(&data), [Sha256::digest(&data[..BLOCK_SIZE]).to_vec(), Sha256::digest([7]).to_vec()]);
+    }
+
+    #[test]
+    fn computes_tree_hash_root() {
+        let hashes: Vec<Vec<u8>> = (0..129u8).map(|x| Sha256::digest([x]).to_vec()).collect();
+        assert_eq!(tree_hash_root(&[]), Sha256::digest([]).to_vec());
+        assert_eq!(tree_hash_root(&hashes[..1]), hashes[0]);
+        assert_eq!(tree_hash_root(&hashes[..2]), Sha256::digest(hashes[..2].concat()).to_vec());
+
+        // A full node of 128, then a node of 1, then their parent
+        let level = [Sha256::digest(hashes[..128].concat()).to_vec(), Sha256::digest(&hashes[128]).to_vec()];
+        assert_eq!(tree_hash_root(&hashes), Sha256::digest(level.concat()).to_vec());
+    }
+
+    #[test]
+    fn writes_computed_hashes_json() {
+        let mut package = Package { files: vec![
+            PackageFile { path: "empty.txt".into(), data: Vec::new() },
+            PackageFile { path: "big.js".into(), data: vec![7u8; BLOCK_SIZE + 1] },
+            PackageFile { path: "_metadata/verified_contents.json".into(), data: b"[]".to_vec() },
+        ] };
+        let b64 = |x: &[u8]| general_purpose::STANDARD.encode(Sha256::digest(x));
+        let expected = json!({
+            "file_hashes": [
+                { "block_hashes": [b64(b"")], "block_size": 4096, "path": "empty.txt" },
+                { "block_hashes": [b64(&[7u8; BLOCK_SIZE]), b64(&[7])], "block_size": 4096, "path": "big.js" },
+            ],
+            "version": 2,
+        });
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&computed_hashes(&package)).unwrap(), expected);
+        assert_eq!(computed_hashes(&Package { files: Vec::new() }), r#"{"file_hashes":[],"version":2}"#);
+
+        // Replaced rather than added twice, and never hashing itself
+        add_computed_hashes(&mut package);
+        add_computed_hashes(&mut package);
+        let files: Vec<_> = package.files.iter().filter(|x| x.path == COMPUTED_HASHES_PATH).collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&files[0].data).unwrap(), expected);
+    }
+}
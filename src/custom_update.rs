@@ -0,0 +1,99 @@
+// Dependencies
+use crate::{updatecheck::{offered, UpdateCheck}, ChromeCRXQuery, Error, ProductId};
+
+/// Downloads an extension from a self-hosted update server, as declared by the `update_url` in its manifest.
+/// 
+/// The standard Omaha query is sent as an updatecheck, since most self-hosted servers are a static `update.xml`
+/// that can't redirect, and the CRX is then fetched from the codebase it lists.
+#[derive(Clone)]
+pub struct CustomUpdateQuery<'a> {
+    /// The manifest's `update_url`, which may already have query parameters.
+    pub update_url: &'a str,
+    /// The extension id.
+    pub id: &'a str,
+    /// The browser version reported to the server.
+    pub prodversion: &'a str,
+    /// Overrides the `User-Agent` header, the reqwest default is used otherwise.
+    pub user_agent: Option<&'a str>,
+}
+impl<'a> CustomUpdateQuery<'a> {
+    /// Creates a query for `id` against `update_url`.
+    pub fn new(update_url: &'a str, id: &'a str) -> Self {
+        Self { update_url, id, prodversion: "9999.0.9999.0", user_agent: None }
+    }
+
+    /// The underlying Omaha query.
+    pub fn query(&self) -> ChromeCRXQuery<'a> {
+        ChromeCRXQuery {
+            endpoint: self.update_url,
+            response: "updatecheck",
+            prod: ProductId::ChromiumCRX,
+            prodversion: self.prodversion,
+            x: self.id,
+            user_agent: self.user_agent,
+            ..Default::default()
+        }
+    }
+
+    /// Asks the server about the CRX, failing with a [`crate::updatecheck::NotServed`] if it doesn't offer one.
+    /// 
+    /// For a blocking version, use [`updatecheck_blocking`].
+    pub async fn updatecheck(&self) -> Result<UpdateCheck, Error> {
+        let client = crate::shared_client();
+        let response = self.query().request(client).send().await.and_then(|x| x.error_for_status())?;
+        offered(&response.bytes().await?, self.id)
+    }
+
+    /// Asks the server about the CRX, failing with a [`crate::updatecheck::NotServed`] if it doesn't offer one.
+    /// 
+    /// For a async version, use [`updatecheck`].
+    pub fn updatecheck_blocking(&self) -> Result<UpdateCheck, Error> {
+        let client = crate::shared_blocking_client();
+        let response = self.query().request_blocking(client).send().and_then(|x| x.error_for_status())?;
+        offered(&response.bytes()?, self.id)
+    }
+
+    /// Asks the server where the CRX is, failing with a [`crate::updatecheck::NotServed`] if it doesn't offer one.
+    /// 
+    /// For a blocking version, use [`codebase_blocking`].
+    pub async fn codebase(&self) -> Result<String, Error> {
+        Ok(self.updatecheck().await?.codebase.unwrap_or_default())
+    }
+
+    /// Asks the server where the CRX is, failing with a [`crate::updatecheck::NotServed`] if it doesn't offer one.
+    /// 
+    /// For a async version, use [`codebase`].
+    pub fn codebase_blocking(&self) -> Result<String, Error> {
+        Ok(self.updatecheck_blocking()?.codebase.unwrap_or_default())
+    }
+
+    /// Downloads the extension, failing with a [`crate::updatecheck::HashMismatch`] if it doesn't have the hash the server reported.
+    /// 
+    /// For a blocking version, use [`download_blocking`].
+    pub async fn download(&self) -> Result<Vec<u8>, Error> {
+        let check = self.updatecheck().await?;
+        let mut request = crate::shared_client().get(check.codebase.as_deref().unwrap_or_default());
+        if let Some(user_agent) = self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        let response = request.send().await.and_then(|x| x.error_for_status())?;
+        let crx = response.bytes().await?.to_vec();
+        check.verify(&crx)?;
+        Ok(crx)
+    }
+
+    /// Downloads the extension, failing with a [`crate::updatecheck::HashMismatch`] if it doesn't have the hash the server reported.
+    /// 
+    /// For a async version, use [`download`].
+    pub fn download_blocking(&self) -> Result<Vec<u8>, Error> {
+        let check = self.updatecheck_blocking()?;
+        let mut request = crate::shared_blocking_client().get(check.codebase.as_deref().unwrap_or_default());
+        if let Some(user_agent) = self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        let response = request.send().and_then(|x| x.error_for_status())?;
+        let crx = response.bytes()?.to_vec();
+        check.verify(&crx)?;
+        Ok(crx)
+    }
+}
@@ -0,0 +1,1205 @@
+// Dependencies
+use std::{fmt, fs::{File, OpenOptions}, io::{self, ErrorKind, Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}, sync::{Arc, OnceLock}, thread, time::{Duration, Instant}};
+use reqwest::{header::{self, HeaderMap}, StatusCode};
+use sha2::{Digest, Sha256};
+use crate::{audit::{AuditEntry, AuditLog}, hex, rate_limit::RateLimiter, RateLimit, updatecheck::offered, verify_extension_id, ChromeCRXQuery, CrxError, CrxToZipWriter, Error, RetryPolicy, VerifyError};
+
+/// Connection settings for the clients of a [`Downloader`].
+/// 
+/// The defaults match reqwest's.
+#[derive(Debug, Clone)]
+pub struct DownloaderOptions {
+    /// The most idle connections kept open per host.
+    pub pool_max_idle_per_host: usize,
+    /// How long idle connections are kept open for, `None` keeps them forever.
+    pub pool_idle_timeout: Option<Duration>,
+    /// The TCP keep-alive interval, `None` disables it.
+    pub tcp_keepalive: Option<Duration>,
+    /// Only use HTTP/2, skipping negotiation.
+    pub http2_prior_knowledge: bool,
+    /// Grow the HTTP/2 flow control windows based on the measured bandwidth-delay product.
+    pub http2_adaptive_window: bool,
+    /// How often HTTP/2 pings are sent to keep connections alive.
+    /// Only applies to the async client.
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// Only use HTTP/3 (QUIC), skipping negotiation.
+    /// Requires the `http3` feature, which in turn needs `RUSTFLAGS="--cfg reqwest_unstable"`.
+    #[cfg(feature = "http3")]
+    pub http3_prior_knowledge: bool,
+    /// Append a JSON line describing every request to this file, see [`AuditLog`].
+    pub audit_log: Option<PathBuf>,
+    /// Download the CRX in up to this many byte ranges in parallel, if the server supports it.
+    /// `1` disables it.
+    /// 
+    /// The segments are held in memory until all of them arrived, so the whole CRX is,
+    /// even by [`Downloader::download_to`] and [`Downloader::download_zip_to`] which otherwise stream it.
+    pub segments: usize,
+    /// The smallest byte range worth its own request when downloading in segments.
+    pub min_segment_size: u64,
+    /// Route every request through this proxy, e.g. `http://host:8080` or `socks5://host:1080`.
+    /// SOCKS proxies need the `socks` feature.
+    pub proxy: Option<String>,
+    /// Honour the `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables when `proxy` isn't set.
+    pub env_proxy: bool,
+    /// Retries for transient failures, off by default.
+    pub retry: RetryPolicy,
+    /// How long connecting may take.
+    pub connect_timeout: Option<Duration>,
+    /// How long a single request may take, from sending it to the end of the body.
+    /// `None` keeps reqwest's default, which is none for the async client and 30 seconds for the blocking one.
+    pub timeout: Option<Duration>,
+    /// How long the body may stall between chunks.
+    /// Only applies to the async client.
+    pub read_timeout: Option<Duration>,
+    /// How long a whole download may take, including retries and fallbacks.
+    pub deadline: Option<Duration>,
+    /// Called as the CRX arrives, see [`Progress`].
+    pub progress: Option<ProgressCallback>,
+    /// Update endpoints to try in order, e.g. a mirror before Google's, instead of the query's own.
+    /// The next one is tried when a request fails, the extension isn't served, or the status is 204 or an error,
+    /// as long as nothing was written yet. The bodies of error responses are dropped while this is set.
+    pub endpoints: Vec<String>,
+    /// Throttles requests and bandwidth, off by default.
+    pub rate_limit: RateLimit,
+    /// Ask for the CRX as-is with `Accept-Encoding: identity`, and never decode it,
+    /// so its size and hash are exactly what the server sent.
+    /// Without this, the `gzip`, `brotli` and `deflate` features decode compressed responses transparently.
+    pub identity_encoding: bool,
+}
+impl Default for DownloaderOptions {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: usize::MAX,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            tcp_keepalive: None,
+            http2_prior_knowledge: false,
+            http2_adaptive_window: false,
+            http2_keep_alive_interval: None,
+            #[cfg(feature = "http3")]
+            http3_prior_knowledge: false,
+            audit_log: None,
+            segments: 1,
+            min_segment_size: 1024 * 1024,
+            proxy: None,
+            env_proxy: true,
+            retry: RetryPolicy::default(),
+            connect_timeout: None,
+            timeout: None,
+            read_timeout: None,
+            deadline: None,
+            progress: None,
+            endpoints: Vec::new(),
+            rate_limit: RateLimit::default(),
+            identity_encoding: false,
+        }
+    }
+}
+
+/// How far along a download is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// The url the CRX is coming from, after following redirects.
+    pub url: String,
+    /// How many bytes were received so far.
+    pub received: u64,
+    /// The size of the CRX, if the server sent a `Content-Length`.
+    pub total: Option<u64>,
+}
+
+/// Receives [`Progress`] updates, once the response headers arrive and then after every chunk.
+/// 
+/// Downloads in segments only report once each segment is written.
+/// A retried request starts over from zero.
+#[derive(Clone)]
+pub struct ProgressCallback(pub Arc<dyn Fn(&Progress) + Send + Sync>);
+impl ProgressCallback {
+    /// Wraps `callback`.
+    pub fn new(callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+}
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// A downloaded body, along with the response metadata useful for integrity checks and debugging throttling.
+#[derive(Debug, Clone)]
+pub struct DownloadResponse {
+    /// The url the body came from, after following redirects.
+    pub url: String,
+    pub status: StatusCode,
+    pub content_length: Option<u64>,
+    /// Google's hashes of the object, e.g. `crc32c=...,md5=...` with base64 encoded digests.
+    pub x_goog_hash: Option<String>,
+    pub date: Option<String>,
+    pub cache_control: Option<String>,
+    pub age: Option<String>,
+    pub expires: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Only set for partial responses, e.g. `bytes 0-1023/4096`.
+    pub content_range: Option<String>,
+    /// The update endpoint the CRX was requested from, see [`DownloaderOptions::endpoints`].
+    pub endpoint: Option<String>,
+    /// How the body was encoded on the wire, e.g. `gzip`.
+    /// Missing if the body was decoded transparently, as `content_length` then is.
+    pub content_encoding: Option<String>,
+    /// How many bytes of body were received, after any decoding.
+    pub received: u64,
+    pub body: Vec<u8>,
+}
+impl DownloadResponse {
+    fn new(url: String, status: StatusCode, headers: &HeaderMap, body: Vec<u8>) -> Self {
+        let get = |name: &str| headers
+            .get(name)
+            .and_then(|x| x.to_str().ok())
+            .map(str::to_string);
+        Self {
+            url,
+            status,
+            content_length: get(header::CONTENT_LENGTH.as_str()).and_then(|x| x.parse().ok()),
+            x_goog_hash: get("x-goog-hash"),
+            date: get(header::DATE.as_str()),
+            cache_control: get(header::CACHE_CONTROL.as_str()),
+            age: get(header::AGE.as_str()),
+            expires: get(header::EXPIRES.as_str()),
+            etag: get(header::ETAG.as_str()),
+            last_modified: get(header::LAST_MODIFIED.as_str()),
+            content_range: get(header::CONTENT_RANGE.as_str()),
+            endpoint: None,
+            content_encoding: get(header::CONTENT_ENCODING.as_str()),
+            received: 0,
+            body,
+        }
+    }
+
+    /// The total size from [`DownloadResponse::content_range`].
+    fn total_size(&self) -> Option<u64> {
+        self.content_range.as_deref()?.rsplit_once('/')?.1.parse().ok()
+    }
+
+    /// Looks up a digest in [`DownloadResponse::x_goog_hash`], e.g. `md5` or `crc32c`, still base64 encoded.
+    pub fn goog_hash(&self, algorithm: &str) -> Option<&str> {
+        self.x_goog_hash
+            .as_deref()?
+            .split(',')
+            .filter_map(|x| x.trim().split_once('='))
+            .find(|(name, _)| *name == algorithm)
+            .map(|(_, digest)| digest)
+    }
+}
+
+/// Downloads many extensions, reusing connections between them.
+/// 
+/// The clients are only built when first used, so a `Downloader` used only for async
+/// downloads never builds a blocking client (which can't be done inside a runtime).
+#[derive(Debug, Default)]
+pub struct Downloader {
+    options: DownloaderOptions,
+    client: OnceLock<reqwest::Client>,
+    blocking_client: OnceLock<reqwest::blocking::Client>,
+    audit_log: Option<AuditLog>,
+    rate_limiter: RateLimiter,
+}
+impl Downloader {
+    /// Creates a downloader with the given connection settings.
+    pub fn new(options: DownloaderOptions) -> Self {
+        Self {
+            audit_log: options.audit_log.as_ref().map(AuditLog::new),
+            rate_limiter: RateLimiter::new(&options.rate_limit),
+            options,
+            ..Default::default()
+        }
+    }
+
+    /// The connection settings.
+    pub fn options(&self) -> &DownloaderOptions {
+        &self.options
+    }
+
+    /// The async client, built on first use.
+    pub fn client(&self) -> Result<&reqwest::Client, Error> {
+        if let Some(client) = self.client.get() {
+            return Ok(client);
+        }
+
+        let options = &self.options;
+        let mut builder = reqwest::Client::builder()
+            .pool_max_idle_per_host(options.pool_max_idle_per_host)
+            .pool_idle_timeout(options.pool_idle_timeout)
+            .tcp_keepalive(options.tcp_keepalive)
+            .http2_adaptive_window(options.http2_adaptive_window)
+            .http2_keep_alive_interval(options.http2_keep_alive_interval);
+        if options.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        #[cfg(feature = "http3")]
+        if options.http3_prior_knowledge {
+            builder = builder.http3_prior_knowledge();
+        }
+        if let Some(proxy) = &options.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        } else if !options.env_proxy {
+            builder = builder.no_proxy();
+        }
+        if let Some(timeout) = options.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = options.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if options.identity_encoding {
+            let headers = HeaderMap::from_iter([(header::ACCEPT_ENCODING, header::HeaderValue::from_static("identity"))]);
+            builder = builder.no_gzip().no_brotli().no_deflate().default_headers(headers);
+        }
+        let client = builder.build()?;
+        Ok(self.client.get_or_init(|| client))
+    }
+
+    /// The blocking client, built on first use.
+    pub fn blocking_client(&self) -> Result<&reqwest::blocking::Client, Error> {
+        if let Some(client) = self.blocking_client.get() {
+            return Ok(client);
+        }
+
+        let options = &self.options;
+        let mut builder = reqwest::blocking::Client::builder()
+            .pool_max_idle_per_host(options.pool_max_idle_per_host)
+            .pool_idle_timeout(options.pool_idle_timeout)
+            .tcp_keepalive(options.tcp_keepalive)
+            .http2_adaptive_window(options.http2_adaptive_window);
+        if options.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        #[cfg(feature = "http3")]
+        if options.http3_prior_knowledge {
+            builder = builder.http3_prior_knowledge();
+        }
+        if let Some(proxy) = &options.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        } else if !options.env_proxy {
+            builder = builder.no_proxy();
+        }
+        if let Some(timeout) = options.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = options.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if options.identity_encoding {
+            let headers = HeaderMap::from_iter([(header::ACCEPT_ENCODING, header::HeaderValue::from_static("identity"))]);
+            builder = builder.no_gzip().no_brotli().no_deflate().default_headers(headers);
+        }
+        let client = builder.build()?;
+        Ok(self.blocking_client.get_or_init(|| client))
+    }
+
+    /// Records a finished request in the audit log, if there is one.
+    fn audit<W: Write>(&self, method: &str, url: &str, started: Instant, body: Tee<W>, outcome: &Result<DownloadResponse, Error>) -> Result<(), Error> {
+        let Some(audit_log) = &self.audit_log else {
+            return Ok(());
+        };
+
+        let mut entry = AuditEntry {
+            method: method.to_string(),
+            url: url.to_string(),
+            bytes: body.bytes,
+            duration: started.elapsed(),
+            ..Default::default()
+        };
+        match outcome {
+            Ok(response) => {
+                entry.final_url = Some(response.url.clone());
+                entry.status = Some(response.status.as_u16());
+                entry.sha256 = Some(hex(&body.hasher.finalize()));
+            },
+            Err(error) => entry.error = Some(error.to_string()),
+        }
+        audit_log.record(&entry)
+    }
+
+    /// Reads the next chunk of the body, within [`DownloaderOptions::read_timeout`].
+    async fn read_chunk(&self, response: &mut reqwest::Response) -> Result<Option<impl AsRef<[u8]>>, Error> {
+        let chunk = response.chunk();
+        let chunk = match self.options.read_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, chunk)
+                .await
+                .map_err(|_| Error::new(ErrorKind::TimedOut, "timed out reading the response body"))?,
+            None => chunk.await,
+        };
+        Ok(chunk?)
+    }
+
+    /// Tells the [`DownloaderOptions::progress`] callback, if there is one.
+    fn report(&self, url: &str, received: u64, total: Option<u64>) {
+        if let Some(progress) = &self.options.progress {
+            (progress.0)(&Progress { url: url.to_string(), received, total });
+        }
+    }
+
+    /// Whether a response with `status` is worth moving on to the next endpoint for, see [`DownloaderOptions::endpoints`].
+    fn falls_back(&self, status: StatusCode) -> bool {
+        !self.options.endpoints.is_empty()
+            && (status == StatusCode::NO_CONTENT || status.is_client_error() || status.is_server_error())
+    }
+
+    /// Whether another attempt should be made, see [`RetryPolicy`].
+    fn should_retry<W>(&self, attempt: u32, body: &Tee<W>, outcome: &Result<DownloadResponse, Error>) -> bool {
+        attempt < self.options.retry.max_attempts
+            && body.bytes == 0
+            && !body.write_failed
+            && outcome.as_ref().map_or(true, |x| RetryPolicy::is_retryable(x.status))
+    }
+
+    /// Sends a request, streaming the body to `out`, retrying as configured.
+    /// The returned response has an empty body, and progress is only reported if `report` is set.
+    async fn fetch_to(&self, request: reqwest::RequestBuilder, out: &mut impl Write, deadline: Option<Instant>, report: bool) -> Result<DownloadResponse, Error> {
+        let request = request.build()?;
+        let (method, url) = (request.method().to_string(), request.url().to_string());
+
+        let mut attempt = 1;
+        loop {
+            let mut request = request.try_clone().ok_or_else(|| Error::other("request can't be sent more than once"))?;
+            limit_timeout(request.timeout_mut(), deadline)?;
+            let retries_left = attempt < self.options.retry.max_attempts;
+            let started = Instant::now();
+
+            if let Some(wait) = self.rate_limiter.request() {
+                tokio::time::sleep(wait).await;
+            }
+            let mut body = Tee::new(&mut *out);
+            let outcome = async {
+                let mut response = self.client()?.execute(request).await?;
+                let metadata = DownloadResponse::new(response.url().to_string(), response.status(), response.headers(), Vec::new());
+                if retries_left && RetryPolicy::is_retryable(metadata.status) || self.falls_back(metadata.status) {
+                    return Ok(metadata);
+                }
+                if report {
+                    self.report(&metadata.url, 0, metadata.content_length);
+                }
+                while let Some(chunk) = self.read_chunk(&mut response).await? {
+                    body.write_all(chunk.as_ref())?;
+                    if let Some(wait) = self.rate_limiter.bytes(chunk.as_ref().len()) {
+                        tokio::time::sleep(wait).await;
+                    }
+                    if report {
+                        self.report(&metadata.url, body.bytes as u64, metadata.content_length);
+                    }
+                }
+                Ok(metadata)
+            }.await;
+
+            let outcome = outcome.map(|x| DownloadResponse { received: body.bytes as u64, ..x });
+            let retry = self.should_retry(attempt, &body, &outcome);
+            self.audit(&method, &url, started, body, &outcome)?;
+            if !retry {
+                return outcome;
+            }
+            tokio::time::sleep(backoff(self.options.retry.backoff(attempt), deadline)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Sends a request, streaming the body to `out`, retrying as configured.
+    /// The returned response has an empty body, and progress is only reported if `report` is set.
+    fn fetch_to_blocking(&self, request: reqwest::blocking::RequestBuilder, out: &mut impl Write, deadline: Option<Instant>, report: bool) -> Result<DownloadResponse, Error> {
+        let request = request.build()?;
+        let (method, url) = (request.method().to_string(), request.url().to_string());
+
+        let mut attempt = 1;
+        loop {
+            let mut request = request.try_clone().ok_or_else(|| Error::other("request can't be sent more than once"))?;
+            limit_timeout(request.timeout_mut(), deadline)?;
+            let retries_left = attempt < self.options.retry.max_attempts;
+            let started = Instant::now();
+
+            if let Some(wait) = self.rate_limiter.request() {
+                thread::sleep(wait);
+            }
+            let mut body = Tee::new(&mut *out);
+            let outcome = (|| {
+                let mut response = self.blocking_client()?.execute(request)?;
+                let metadata = DownloadResponse::new(response.url().to_string(), response.status(), response.headers(), Vec::new());
+                if retries_left && RetryPolicy::is_retryable(metadata.status) || self.falls_back(metadata.status) {
+                    return Ok(metadata);
+                }
+                if report {
+                    self.report(&metadata.url, 0, metadata.content_length);
+                }
+                let mut buffer = vec![0; 64 * 1024];
+                loop {
+                    let read = match response.read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(read) => read,
+                        Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                        Err(error) => return Err(error.into()),
+                    };
+                    body.write_all(&buffer[..read])?;
+                    if let Some(wait) = self.rate_limiter.bytes(read) {
+                        thread::sleep(wait);
+                    }
+                    if report {
+                        self.report(&metadata.url, body.bytes as u64, metadata.content_length);
+                    }
+                }
+                Ok(metadata)
+            })();
+
+            let outcome = outcome.map(|x| DownloadResponse { received: body.bytes as u64, ..x });
+            let retry = self.should_retry(attempt, &body, &outcome);
+            self.audit(&method, &url, started, body, &outcome)?;
+            if !retry {
+                return outcome;
+            }
+            thread::sleep(backoff(self.options.retry.backoff(attempt), deadline));
+            attempt += 1;
+        }
+    }
+
+    /// Sends a request, reading the whole body.
+    async fn fetch(&self, request: reqwest::RequestBuilder, deadline: Option<Instant>) -> Result<DownloadResponse, Error> {
+        let mut body = Vec::new();
+        let response = self.fetch_to(request, &mut body, deadline, false).await?;
+        Ok(DownloadResponse { body, ..response })
+    }
+
+    /// Sends a request, reading the whole body.
+    fn fetch_blocking(&self, request: reqwest::blocking::RequestBuilder, deadline: Option<Instant>) -> Result<DownloadResponse, Error> {
+        let mut body = Vec::new();
+        let response = self.fetch_to_blocking(request, &mut body, deadline, false)?;
+        Ok(DownloadResponse { body, ..response })
+    }
+
+    /// Splits `total` bytes into the ranges to download, see [`DownloaderOptions::segments`].
+    /// An empty body has none.
+    fn segments(&self, total: u64) -> Vec<(u64, u64)> {
+        if total == 0 {
+            return Vec::new();
+        }
+        let count = total
+            .div_ceil(self.options.min_segment_size.max(1))
+            .clamp(1, self.options.segments.max(1) as u64);
+        let size = total.div_ceil(count);
+        (0..count)
+            .map(|i| (i * size, ((i + 1) * size).min(total) - 1))
+            .filter(|(start, end)| start <= end)
+            .collect()
+    }
+
+    /// Checks a segment is the range that was asked for.
+    fn check_segment(response: &DownloadResponse, (start, end): (u64, u64)) -> Result<(), Error> {
+        if response.status != StatusCode::PARTIAL_CONTENT || response.body.len() as u64 != end - start + 1 {
+            return Err(Error::new(ErrorKind::InvalidData, format!("segment {}-{} failed with status {}", start, end, response.status)));
+        }
+        Ok(())
+    }
+
+    /// Sends a request with extra `headers`, streaming the body to `out`, in parallel segments if enabled.
+    /// Requests that already ask for a range aren't split up.
+    /// 
+    /// A single byte is requested first, which reveals whether ranges are supported, the total size and the final url.
+    /// Servers that ignore the range send the whole body instead, which is used as-is.
+    async fn fetch_to_segmented(&self, request: reqwest::RequestBuilder, headers: &HeaderMap, out: &mut impl Write, deadline: Option<Instant>) -> Result<DownloadResponse, Error> {
+        let request = request.headers(headers.clone());
+        if self.options.segments <= 1 || headers.contains_key(header::RANGE) {
+            return self.fetch_to(request, out, deadline, true).await;
+        }
+
+        let mut probe = self.fetch(request.header(header::RANGE, "bytes=0-0"), deadline).await?;
+        let Some(total) = probe.total_size().filter(|_| probe.status == StatusCode::PARTIAL_CONTENT) else {
+            out.write_all(&probe.body)?;
+            self.report(&probe.url, probe.body.len() as u64, probe.content_length);
+            probe.body.clear();
+            return Ok(probe);
+        };
+
+        // Fetch every segment, then write them in order
+        let client = self.client()?;
+        let ranges = self.segments(total);
+        let segments = futures_util::future::try_join_all(ranges.iter().map(|(start, end)| {
+            self.fetch(client.get(&probe.url).header(header::RANGE, format!("bytes={}-{}", start, end)), deadline)
+        })).await?;
+        for (segment, range) in segments.iter().zip(ranges) {
+            Self::check_segment(segment, range)?;
+            out.write_all(&segment.body)?;
+            self.report(&probe.url, range.1 + 1, Some(total));
+        }
+
+        // Done
+        probe.body.clear();
+        Ok(DownloadResponse { status: StatusCode::OK, content_length: Some(total), content_range: None, received: total, ..probe })
+    }
+
+    /// Sends a request with extra `headers`, streaming the body to `out`, in parallel segments if enabled.
+    /// Requests that already ask for a range aren't split up.
+    /// 
+    /// A single byte is requested first, which reveals whether ranges are supported, the total size and the final url.
+    /// Servers that ignore the range send the whole body instead, which is used as-is.
+    fn fetch_to_segmented_blocking(&self, request: reqwest::blocking::RequestBuilder, headers: &HeaderMap, out: &mut impl Write, deadline: Option<Instant>) -> Result<DownloadResponse, Error> {
+        let request = request.headers(headers.clone());
+        if self.options.segments <= 1 || headers.contains_key(header::RANGE) {
+            return self.fetch_to_blocking(request, out, deadline, true);
+        }
+
+        let mut probe = self.fetch_blocking(request.header(header::RANGE, "bytes=0-0"), deadline)?;
+        let Some(total) = probe.total_size().filter(|_| probe.status == StatusCode::PARTIAL_CONTENT) else {
+            out.write_all(&probe.body)?;
+            self.report(&probe.url, probe.body.len() as u64, probe.content_length);
+            probe.body.clear();
+            return Ok(probe);
+        };
+
+        // Fetch every segment, then write them in order
+        let client = self.blocking_client()?;
+        let ranges = self.segments(total);
+        let segments = thread::scope(|scope| {
+            let handles: Vec<_> = ranges
+                .iter()
+                .map(|(start, end)| {
+                    let request = client.get(&probe.url).header(header::RANGE, format!("bytes={}-{}", start, end));
+                    scope.spawn(move || self.fetch_blocking(request, deadline))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|x| x.join().unwrap_or_else(|_| Err(Error::other("segment download panicked"))))
+                .collect::<Result<Vec<_>, Error>>()
+        })?;
+        for (segment, range) in segments.iter().zip(ranges) {
+            Self::check_segment(segment, range)?;
+            out.write_all(&segment.body)?;
+            self.report(&probe.url, range.1 + 1, Some(total));
+        }
+
+        // Done
+        probe.body.clear();
+        Ok(DownloadResponse { status: StatusCode::OK, content_length: Some(total), content_range: None, received: total, ..probe })
+    }
+
+    /// Downloads the extension, streaming the CRX to `out`.
+    /// The returned response has an empty body.
+    /// 
+    /// If the store answers a redirect query with 204, the query is retried as an updatecheck to find out why.
+    /// Should it still offer a CRX, that is downloaded instead, otherwise the reason is returned as a [`crate::updatecheck::NotServed`].
+    /// That CRX is checked against the hash the updatecheck reported, see [`crate::updatecheck::HashMismatch`].
+    /// For a blocking version, use [`download_to_blocking`].
+    pub async fn download_to(&self, query: &ChromeCRXQuery<'_>, out: &mut impl Write) -> Result<DownloadResponse, Error> {
+        self.download_to_with_headers(query, out, HeaderMap::new()).await
+    }
+
+    /// [`Downloader::download_to`], sending extra headers with the CRX requests.
+    async fn download_to_with_headers(&self, query: &ChromeCRXQuery<'_>, out: &mut impl Write, headers: HeaderMap) -> Result<DownloadResponse, Error> {
+        let mut out = IdCheck::new(out, query.verify_id.then_some(query.x));
+        let response = self.download_endpoints(query, &mut out, headers).await?;
+        out.finish(response.status)?;
+        Ok(response)
+    }
+
+    /// Downloads the extension from the first of [`DownloaderOptions::endpoints`] that serves it, or the query's own.
+    async fn download_endpoints(&self, query: &ChromeCRXQuery<'_>, out: &mut impl Write, headers: HeaderMap) -> Result<DownloadResponse, Error> {
+        let deadline = self.options.deadline.map(|x| Instant::now() + x);
+        if self.options.endpoints.is_empty() {
+            return self.download_from(query, out, &headers, deadline).await;
+        }
+
+        // Try each endpoint until one serves the extension
+        let mut last = None;
+        for endpoint in &self.options.endpoints {
+            let query = ChromeCRXQuery { endpoint, ..query.clone() };
+            let mut body = Tee::new(&mut *out);
+            let result = self.download_from(&query, &mut body, &headers, deadline).await;
+            if body.bytes > 0 || result.as_ref().is_ok_and(|x| !self.falls_back(x.status)) {
+                return result;
+            }
+            last = Some(result);
+        }
+        last.unwrap_or_else(|| Err(Error::other("no endpoints")))
+    }
+
+    /// Downloads the extension from the query's endpoint, see [`Downloader::download_to`].
+    async fn download_from(&self, query: &ChromeCRXQuery<'_>, out: &mut impl Write, headers: &HeaderMap, deadline: Option<Instant>) -> Result<DownloadResponse, Error> {
+        let client = self.client()?;
+        let response = self.fetch_to_segmented(query.request(client), headers, out, deadline).await?;
+        if response.status != StatusCode::NO_CONTENT || query.response != "redirect" {
+            return Ok(DownloadResponse { endpoint: Some(query.endpoint.to_string()), ..response });
+        }
+
+        let updatecheck = ChromeCRXQuery { response: "updatecheck", ..query.clone() };
+        let xml = self.fetch(updatecheck.request(client), deadline).await?.body;
+        let check = offered(&xml, query.x)?;
+        let mut body = Tee::new(&mut *out);
+        let response = self.fetch_to_segmented(client.get(check.codebase.as_deref().unwrap_or_default()), headers, &mut body, deadline).await?;
+        if response.status.is_success() {
+            check.verify_sha256(&body.hasher.finalize())?;
+        }
+        Ok(DownloadResponse { endpoint: Some(query.endpoint.to_string()), ..response })
+    }
+
+    /// Downloads the extension, streaming the CRX to `out`.
+    /// The returned response has an empty body.
+    /// 
+    /// If the store answers a redirect query with 204, the query is retried as an updatecheck to find out why.
+    /// Should it still offer a CRX, that is downloaded instead, otherwise the reason is returned as a [`crate::updatecheck::NotServed`].
+    /// That CRX is checked against the hash the updatecheck reported, see [`crate::updatecheck::HashMismatch`].
+    /// For a async version, use [`download_to`].
+    pub fn download_to_blocking(&self, query: &ChromeCRXQuery<'_>, out: &mut impl Write) -> Result<DownloadResponse, Error> {
+        self.download_to_with_headers_blocking(query, out, HeaderMap::new())
+    }
+
+    /// [`Downloader::download_to_blocking`], sending extra headers with the CRX requests.
+    fn download_to_with_headers_blocking(&self, query: &ChromeCRXQuery<'_>, out: &mut impl Write, headers: HeaderMap) -> Result<DownloadResponse, Error> {
+        let mut out = IdCheck::new(out, query.verify_id.then_some(query.x));
+        let response = self.download_endpoints_blocking(query, &mut out, headers)?;
+        out.finish(response.status)?;
+        Ok(response)
+    }
+
+    /// Downloads the extension from the first of [`DownloaderOptions::endpoints`] that serves it, or the query's own.
+    fn download_endpoints_blocking(&self, query: &ChromeCRXQuery<'_>, out: &mut impl Write, headers: HeaderMap) -> Result<DownloadResponse, Error> {
+        let deadline = self.options.deadline.map(|x| Instant::now() + x);
+        if self.options.endpoints.is_empty() {
+            return self.download_from_blocking(query, out, &headers, deadline);
+        }
+
+        // Try each endpoint until one serves the extension
+        let mut last = None;
+        for endpoint in &self.options.endpoints {
+            let query = ChromeCRXQuery { endpoint, ..query.clone() };
+            let mut body = Tee::new(&mut *out);
+            let result = self.download_from_blocking(&query, &mut body, &headers, deadline);
+            if body.bytes > 0 || result.as_ref().is_ok_and(|x| !self.falls_back(x.status)) {
+                return result;
+            }
+            last = Some(result);
+        }
+        last.unwrap_or_else(|| Err(Error::other("no endpoints")))
+    }
+
+    /// Downloads the extension from the query's endpoint, see [`Downloader::download_to_blocking`].
+    fn download_from_blocking(&self, query: &ChromeCRXQuery<'_>, out: &mut impl Write, headers: &HeaderMap, deadline: Option<Instant>) -> Result<DownloadResponse, Error> {
+        let client = self.blocking_client()?;
+        let response = self.fetch_to_segmented_blocking(query.request_blocking(client), headers, out, deadline)?;
+        if response.status != StatusCode::NO_CONTENT || query.response != "redirect" {
+            return Ok(DownloadResponse { endpoint: Some(query.endpoint.to_string()), ..response });
+        }
+
+        let updatecheck = ChromeCRXQuery { response: "updatecheck", ..query.clone() };
+        let xml = self.fetch_blocking(updatecheck.request_blocking(client), deadline)?.body;
+        let check = offered(&xml, query.x)?;
+        let mut body = Tee::new(&mut *out);
+        let response = self.fetch_to_segmented_blocking(client.get(check.codebase.as_deref().unwrap_or_default()), headers, &mut body, deadline)?;
+        if response.status.is_success() {
+            check.verify_sha256(&body.hasher.finalize())?;
+        }
+        Ok(DownloadResponse { endpoint: Some(query.endpoint.to_string()), ..response })
+    }
+
+    /// Downloads the extension, converting it to a ZIP as it arrives, see [`CrxToZipWriter`].
+    /// The full CRX is never held in memory, unless downloading in [`DownloaderOptions::segments`].
+    /// The returned response has an empty body.
+    /// For a blocking version, use [`download_zip_to_blocking`].
+    pub async fn download_zip_to(&self, query: &ChromeCRXQuery<'_>, out: &mut impl Write) -> Result<DownloadResponse, Error> {
+        let mut zip = CrxToZipWriter::new(out);
+        let response = self.download_to(query, &mut zip).await?;
+        zip.finish()?;
+        Ok(response)
+    }
+
+    /// Downloads the extension, converting it to a ZIP as it arrives, see [`CrxToZipWriter`].
+    /// The full CRX is never held in memory, unless downloading in [`DownloaderOptions::segments`].
+    /// The returned response has an empty body.
+    /// For a async version, use [`download_zip_to`].
+    pub fn download_zip_to_blocking(&self, query: &ChromeCRXQuery<'_>, out: &mut impl Write) -> Result<DownloadResponse, Error> {
+        let mut zip = CrxToZipWriter::new(out);
+        let response = self.download_to_blocking(query, &mut zip)?;
+        zip.finish()?;
+        Ok(response)
+    }
+
+    /// Downloads the extension into `path`, replacing it atomically once the whole CRX arrived, see [`crate::fs::write_atomic_with`].
+    /// The returned response has an empty body.
+    /// 
+    /// Unlike [`Downloader::download_to`], a response that isn't a success or is empty is an error, so `path` is never replaced by an error page or an empty file.
+    /// For a blocking version, use [`download_to_path_blocking`].
+    pub async fn download_to_path(&self, query: &ChromeCRXQuery<'_>, path: impl AsRef<Path>) -> Result<DownloadResponse, Error> {
+        let mut response = None;
+        let slot = &mut response;
+        crate::fs::write_atomic_with_async(path, |mut file| async move {
+            *slot = Some(check_success(self.download_to(query, &mut file).await?)?);
+            Ok(file)
+        }).await?;
+        response.ok_or_else(|| Error::other("download produced no response"))
+    }
+
+    /// Downloads the extension into `path`, replacing it atomically once the whole CRX arrived, see [`crate::fs::write_atomic_with`].
+    /// The returned response has an empty body.
+    /// 
+    /// Unlike [`Downloader::download_to_blocking`], a response that isn't a success or is empty is an error, so `path` is never replaced by an error page or an empty file.
+    /// For a async version, use [`download_to_path`].
+    pub fn download_to_path_blocking(&self, query: &ChromeCRXQuery<'_>, path: impl AsRef<Path>) -> Result<DownloadResponse, Error> {
+        let mut response = None;
+        crate::fs::write_atomic_with(path, |file| {
+            response = Some(check_success(self.download_to_blocking(query, file)?)?);
+            Ok(())
+        })?;
+        response.ok_or_else(|| Error::other("download produced no response"))
+    }
+
+    /// Downloads the extension as a ZIP into `path`, replacing it atomically once done, see [`Downloader::download_to_path`].
+    /// For a blocking version, use [`download_zip_to_path_blocking`].
+    pub async fn download_zip_to_path(&self, query: &ChromeCRXQuery<'_>, path: impl AsRef<Path>) -> Result<DownloadResponse, Error> {
+        let mut response = None;
+        let slot = &mut response;
+        crate::fs::write_atomic_with_async(path, |mut file| async move {
+            *slot = Some(check_success(self.download_zip_to(query, &mut file).await?)?);
+            Ok(file)
+        }).await?;
+        response.ok_or_else(|| Error::other("download produced no response"))
+    }
+
+    /// Downloads the extension as a ZIP into `path`, replacing it atomically once done, see [`Downloader::download_to_path_blocking`].
+    /// For a async version, use [`download_zip_to_path`].
+    pub fn download_zip_to_path_blocking(&self, query: &ChromeCRXQuery<'_>, path: impl AsRef<Path>) -> Result<DownloadResponse, Error> {
+        let mut response = None;
+        crate::fs::write_atomic_with(path, |file| {
+            response = Some(check_success(self.download_zip_to_blocking(query, file)?)?);
+            Ok(())
+        })?;
+        response.ok_or_else(|| Error::other("download produced no response"))
+    }
+
+    /// Downloads the extension, keeping the response metadata.
+    /// See [`Downloader::download_to`] for how unserved extensions are handled.
+    /// For a blocking version, use [`download_response_blocking`].
+    pub async fn download_response(&self, query: &ChromeCRXQuery<'_>) -> Result<DownloadResponse, Error> {
+        let mut body = Vec::new();
+        let response = self.download_to(query, &mut body).await?;
+        Ok(DownloadResponse { body, ..response })
+    }
+
+    /// Downloads the extension, keeping the response metadata.
+    /// See [`Downloader::download_to_blocking`] for how unserved extensions are handled.
+    /// For a async version, use [`download_response`].
+    pub fn download_response_blocking(&self, query: &ChromeCRXQuery<'_>) -> Result<DownloadResponse, Error> {
+        let mut body = Vec::new();
+        let response = self.download_to_blocking(query, &mut body)?;
+        Ok(DownloadResponse { body, ..response })
+    }
+
+    /// Downloads the extension, see [`Downloader::download_response`].
+    /// For a blocking version, use [`download_blocking`].
+    pub async fn download(&self, query: &ChromeCRXQuery<'_>) -> Result<Vec<u8>, Error> {
+        Ok(self.download_response(query).await?.body)
+    }
+
+    /// Downloads the extension, see [`Downloader::download_response_blocking`].
+    /// For a async version, use [`download`].
+    pub fn download_blocking(&self, query: &ChromeCRXQuery<'_>) -> Result<Vec<u8>, Error> {
+        Ok(self.download_response_blocking(query)?.body)
+    }
+
+    /// Downloads the extension only if it changed since it was cached, see [`Validators`].
+    /// For a blocking version, use [`download_if_modified_blocking`].
+    pub async fn download_if_modified(&self, query: &ChromeCRXQuery<'_>, cached: &Validators) -> Result<Conditional, Error> {
+        let mut body = Vec::new();
+        let response = self.download_to_with_headers(query, &mut body, cached.headers()?).await?;
+        Ok(Conditional::new(DownloadResponse { body, ..response }))
+    }
+
+    /// Downloads the extension only if it changed since it was cached, see [`Validators`].
+    /// For a async version, use [`download_if_modified`].
+    pub fn download_if_modified_blocking(&self, query: &ChromeCRXQuery<'_>, cached: &Validators) -> Result<Conditional, Error> {
+        let mut body = Vec::new();
+        let response = self.download_to_with_headers_blocking(query, &mut body, cached.headers()?)?;
+        Ok(Conditional::new(DownloadResponse { body, ..response }))
+    }
+
+    /// Continues downloading the extension into `path`, asking only for the bytes it doesn't have yet.
+    /// The returned response has an empty body.
+    /// 
+    /// The `ETag` or `Last-Modified` of what is in `path` is kept next to it, see [`validators_path`], and sent as `If-Range`.
+    /// Should the CRX have changed since, or the server send the whole CRX anyway, `path` is overwritten with it.
+    /// A partial download without them is started over, as there is no way to tell whether the rest would match.
+    /// If it fails, `path` is left as it was so it can be resumed again.
+    /// For a blocking version, use [`download_resume_blocking`].
+    pub async fn download_resume(&self, query: &ChromeCRXQuery<'_>, path: impl AsRef<Path>) -> Result<DownloadResponse, Error> {
+        let (mut file, offset, validators) = open_resume(path.as_ref())?;
+        let headers = validators.as_ref().map(|x| x.range_headers(offset)).transpose()?.unwrap_or_default();
+        let response = self.download_to_with_headers(query, &mut file, headers).await;
+        check_resumed_id(query, path.as_ref(), finish_resume(path.as_ref(), file, offset, validators, response)?)
+    }
+
+    /// Continues downloading the extension into `path`, asking only for the bytes it doesn't have yet.
+    /// The returned response has an empty body.
+    /// 
+    /// The `ETag` or `Last-Modified` of what is in `path` is kept next to it, see [`validators_path`], and sent as `If-Range`.
+    /// Should the CRX have changed since, or the server send the whole CRX anyway, `path` is overwritten with it.
+    /// A partial download without them is started over, as there is no way to tell whether the rest would match.
+    /// If it fails, `path` is left as it was so it can be resumed again.
+    /// For a async version, use [`download_resume`].
+    pub fn download_resume_blocking(&self, query: &ChromeCRXQuery<'_>, path: impl AsRef<Path>) -> Result<DownloadResponse, Error> {
+        let (mut file, offset, validators) = open_resume(path.as_ref())?;
+        let headers = validators.as_ref().map(|x| x.range_headers(offset)).transpose()?.unwrap_or_default();
+        let response = self.download_to_with_headers_blocking(query, &mut file, headers);
+        check_resumed_id(query, path.as_ref(), finish_resume(path.as_ref(), file, offset, validators, response)?)
+    }
+}
+
+/// Turns a response that isn't a success, or has no body, into an error.
+fn check_success(response: DownloadResponse) -> Result<DownloadResponse, Error> {
+    if !response.status.is_success() {
+        return Err(Error::Status(response.status));
+    }
+    if response.status == StatusCode::NO_CONTENT || response.received == 0 {
+        return Err(Error::EmptyResponse(response.status));
+    }
+    Ok(response)
+}
+
+/// Where [`Downloader::download_resume`] keeps the validators of a partial download, `path` with `.validators` appended.
+pub fn validators_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut validators = path.as_ref().as_os_str().to_owned();
+    validators.push(".validators");
+    validators.into()
+}
+
+/// Opens a partial download for appending, along with how much of it there is and the validators it was downloaded with.
+/// 
+/// The validators are removed until the download finishes, so one that is cut short is started over next time.
+fn open_resume(path: &Path) -> Result<(File, u64, Option<Validators>), Error> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let validators = match std::fs::read_to_string(validators_path(path)) {
+        Ok(validators) => Validators::parse(&validators),
+        Err(error) if error.kind() == ErrorKind::NotFound => Validators::default(),
+        Err(error) => return Err(error.into()),
+    };
+    match std::fs::remove_file(validators_path(path)) {
+        Err(error) if error.kind() != ErrorKind::NotFound => return Err(error.into()),
+        _ => {},
+    }
+
+    // Without a validator there is no telling what the rest would belong to
+    if validators.if_range().is_none() {
+        file.set_len(0)?;
+        return Ok((file, 0, None));
+    }
+    let offset = file.metadata()?.len();
+    Ok((file, offset, Some(validators).filter(|_| offset > 0)))
+}
+
+/// Tidies up `path` after resuming from `offset`, depending on what the server sent.
+/// 
+/// The validators of whatever `path` now holds are put back next to it.
+fn finish_resume(path: &Path, file: File, offset: u64, validators: Option<Validators>, response: Result<DownloadResponse, Error>) -> Result<DownloadResponse, Error> {
+    let restore = |validators: Option<Validators>| match validators {
+        Some(validators) => std::fs::write(validators_path(path), validators.to_string()),
+        None => Ok(()),
+    };
+
+    // Anything appended is unusable if the request failed
+    let response = match response {
+        Ok(response) => response,
+        Err(error) => {
+            file.set_len(offset)?;
+            restore(validators)?;
+            return Err(error);
+        },
+    };
+    if offset == 0 {
+        if response.status.is_success() {
+            restore(Some(Validators::from(&response)))?;
+        }
+        return Ok(response);
+    }
+
+    match response.status {
+        StatusCode::PARTIAL_CONTENT if response.content_range.as_deref().is_some_and(|x| x.starts_with(&format!("bytes {}-", offset))) => restore(Some(Validators::from(&response)))?,
+        StatusCode::PARTIAL_CONTENT => {
+            file.set_len(offset)?;
+            restore(validators)?;
+            return Err(Error::new(ErrorKind::InvalidData, format!("server resumed from the wrong offset: {}", response.content_range.unwrap_or_default())));
+        },
+        // The whole CRX came after the old partial one, move it to the start
+        StatusCode::OK => {
+            let length = file.metadata()?.len() - offset;
+            let mut reader = File::open(path)?;
+            reader.seek(SeekFrom::Start(offset))?;
+            let mut writer = OpenOptions::new().write(true).open(path)?;
+            io::copy(&mut reader.take(length), &mut writer)?;
+            file.set_len(length)?;
+            restore(Some(Validators::from(&response)))?;
+        },
+        // Nothing is left to download, or an error page was appended
+        _ => {
+            file.set_len(offset)?;
+            restore(validators)?;
+        },
+    }
+    Ok(response)
+}
+
+/// Ensures a resumed CRX is of the extension asked for, see [`ChromeCRXQuery::verify_id`].
+/// 
+/// Only the rest of the CRX was checked while downloading, which doesn't include the header the id is in.
+fn check_resumed_id(query: &ChromeCRXQuery<'_>, path: &Path, response: DownloadResponse) -> Result<DownloadResponse, Error> {
+    if query.verify_id && response.status == StatusCode::PARTIAL_CONTENT {
+        let mut check = IdCheck::new(io::sink(), Some(query.x));
+        io::copy(&mut File::open(path)?, &mut check)?;
+        check.finish(StatusCode::OK)?;
+    }
+    Ok(response)
+}
+
+/// What is known about a cached CRX, for [`Downloader::download_if_modified`].
+/// 
+/// Take them from the [`DownloadResponse`] the CRX came with, via `From`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    /// Sent as `If-None-Match`.
+    pub etag: Option<String>,
+    /// Sent as `If-Modified-Since`.
+    pub last_modified: Option<String>,
+}
+impl Validators {
+    /// The conditional request headers.
+    fn headers(&self) -> Result<HeaderMap, Error> {
+        let mut headers = HeaderMap::new();
+        let value = |x: &str| header::HeaderValue::from_str(x).map_err(|x| Error::new(ErrorKind::InvalidInput, x));
+        if let Some(etag) = &self.etag {
+            headers.insert(header::IF_NONE_MATCH, value(etag)?);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.insert(header::IF_MODIFIED_SINCE, value(last_modified)?);
+        }
+        Ok(headers)
+    }
+
+    /// The value to send as `If-Range`, a strong `ETag` or else `Last-Modified`.
+    fn if_range(&self) -> Option<&str> {
+        self.etag.as_deref().filter(|x| !x.starts_with("W/")).or(self.last_modified.as_deref())
+    }
+
+    /// The headers asking for everything from `offset`, as long as it's still the same CRX.
+    fn range_headers(&self, offset: u64) -> Result<HeaderMap, Error> {
+        let mut headers = HeaderMap::new();
+        let value = |x: &str| header::HeaderValue::from_str(x).map_err(|x| Error::new(ErrorKind::InvalidData, x));
+        headers.insert(header::RANGE, value(&format!("bytes={}-", offset))?);
+        headers.insert(header::IF_RANGE, value(self.if_range().unwrap_or_default())?);
+        Ok(headers)
+    }
+
+    /// Parses what [`Validators`] displays as, ignoring anything else.
+    fn parse(text: &str) -> Self {
+        let mut validators = Self::default();
+        for (name, value) in text.lines().filter_map(|x| x.split_once(": ")) {
+            match name {
+                "ETag" => validators.etag = Some(value.to_string()),
+                "Last-Modified" => validators.last_modified = Some(value.to_string()),
+                _ => {},
+            }
+        }
+        validators
+    }
+}
+impl fmt::Display for Validators {
+    /// Writes them as header lines, e.g. `ETag: "abc"`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(etag) = &self.etag {
+            writeln!(f, "ETag: {}", etag)?;
+        }
+        if let Some(last_modified) = &self.last_modified {
+            writeln!(f, "Last-Modified: {}", last_modified)?;
+        }
+        Ok(())
+    }
+}
+impl From<&DownloadResponse> for Validators {
+    fn from(value: &DownloadResponse) -> Self {
+        Self { etag: value.etag.clone(), last_modified: value.last_modified.clone() }
+    }
+}
+
+/// The result of [`Downloader::download_if_modified`].
+#[derive(Debug, Clone)]
+pub enum Conditional {
+    /// The CRX changed, or the server ignored the validators.
+    Modified(DownloadResponse),
+    /// The cached CRX is still current, the response has an empty body.
+    NotModified(DownloadResponse),
+}
+impl Conditional {
+    fn new(response: DownloadResponse) -> Self {
+        match response.status {
+            StatusCode::NOT_MODIFIED => Self::NotModified(response),
+            _ => Self::Modified(response),
+        }
+    }
+}
+
+/// Shortens a request's timeout to what is left before `deadline`, failing if it already passed.
+fn limit_timeout(timeout: &mut Option<Duration>, deadline: Option<Instant>) -> Result<(), Error> {
+    let Some(deadline) = deadline else {
+        return Ok(());
+    };
+    let remaining = deadline
+        .checked_duration_since(Instant::now())
+        .filter(|x| !x.is_zero())
+        .ok_or_else(|| Error::new(ErrorKind::TimedOut, "download deadline exceeded"))?;
+    *timeout = Some(timeout.map_or(remaining, |x| x.min(remaining)));
+    Ok(())
+}
+
+/// Shortens a wait between retries to what is left before `deadline`.
+fn backoff(wait: Duration, deadline: Option<Instant>) -> Duration {
+    match deadline {
+        Some(deadline) => wait.min(deadline.saturating_duration_since(Instant::now())),
+        None => wait,
+    }
+}
+
+/// Holds back the header of a CRX until it is complete, and only forwards it if it is of the `expected` extension.
+/// 
+/// Bodies that don't start like a CRX, such as error pages or resumed downloads, are forwarded as they are.
+struct IdCheck<'a, W> {
+    inner: W,
+    expected: Option<&'a str>,
+    /// The start of the body, while it is held back.
+    header: Vec<u8>,
+    /// Whether the header was held back until now, or the id was already checked.
+    holding: bool,
+    checked: bool,
+}
+impl<'a, W: Write> IdCheck<'a, W> {
+    /// Checks the body is of `expected`, or does nothing if it's `None`.
+    fn new(inner: W, expected: Option<&'a str>) -> Self {
+        Self { inner, expected, header: Vec::new(), holding: expected.is_some(), checked: false }
+    }
+
+    /// Checks the held back header once it is complete, then forwards it.
+    fn release(&mut self) -> Result<(), Error> {
+        if self.header.starts_with(b"Cr24") {
+            match crate::header::parse_header(&self.header) {
+                Err(Error::Crx(CrxError::TruncatedHeader)) => return Ok(()),
+                Err(error) => return Err(error),
+                Ok(_) => verify_extension_id(&self.header, self.expected.unwrap_or_default())?,
+            }
+            self.checked = true;
+        } else if b"Cr24".starts_with(&self.header) {
+            return Ok(());
+        }
+        self.holding = false;
+        Ok(self.inner.write_all(&std::mem::take(&mut self.header))?)
+    }
+
+    /// Forwards anything still held back, failing if a successful response had no complete CRX header.
+    fn finish(mut self, status: StatusCode) -> Result<(), Error> {
+        if self.holding {
+            self.holding = false;
+            self.inner.write_all(&self.header)?;
+        }
+        if self.expected.is_some() && status == StatusCode::OK && !self.checked {
+            let error = match self.header.starts_with(b"Cr24") {
+                true => CrxError::TruncatedHeader,
+                false => CrxError::NotACrx,
+            };
+            return Err(VerifyError::malformed(error).into());
+        }
+        Ok(())
+    }
+}
+impl<W: Write> Write for IdCheck<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        if !self.holding {
+            return self.inner.write(buf);
+        }
+        self.header.extend_from_slice(buf);
+        self.release()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.inner.flush()
+    }
+}
+
+/// Counts and hashes everything written through it, for the audit log and retries.
+struct Tee<W> {
+    inner: W,
+    bytes: usize,
+    hasher: Sha256,
+    /// Whether writing failed, which is never worth retrying the request for.
+    write_failed: bool,
+}
+impl<W: Write> Tee<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, bytes: 0, hasher: Sha256::new(), write_failed: false }
+    }
+}
+impl<W: Write> Write for Tee<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        let written = self.inner.write(buf).inspect_err(|_| self.write_failed = true)?;
+        self.bytes += written;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_cover_total() {
+        let downloader = Downloader::new(DownloaderOptions { segments: 4, min_segment_size: 1, ..Default::default() });
+        assert_eq!(downloader.segments(0), []);
+        assert_eq!(downloader.segments(1), [(0, 0)]);
+        assert_eq!(downloader.segments(10), [(0, 2), (3, 5), (6, 8), (9, 9)]);
+    }
+
+    #[test]
+    fn validators_round_trip() {
+        let validators = Validators { etag: Some("W/\"1\"".into()), last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".into()) };
+        assert_eq!(Validators::parse(&validators.to_string()), validators);
+        assert_eq!(validators.if_range(), validators.last_modified.as_deref());
+        assert_eq!(Validators { etag: Some("\"1\"".into()), ..validators }.if_range(), Some("\"1\""));
+        assert_eq!(Validators::default().if_range(), None);
+    }
+
+    #[test]
+    fn id_check_holds_back_header() {
+        let crx = crate::mock::crx3(b"payload");
+        let id = crate::crx_extension_id(&crx).unwrap();
+
+        let mut out = Vec::new();
+        let mut check = IdCheck::new(&mut out, Some(&id));
+        for byte in &crx {
+            check.write_all(std::slice::from_ref(byte)).unwrap();
+        }
+        check.finish(StatusCode::OK).unwrap();
+        assert_eq!(out, crx);
+
+        let mut out = Vec::new();
+        let mut check = IdCheck::new(&mut out, Some("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"));
+        let error = check.write_all(&crx).unwrap_err();
+        assert!(matches!(Error::from(error), Error::SignatureInvalid(VerifyError::IdMismatch { .. })));
+        assert!(out.is_empty());
+
+        let mut out = Vec::new();
+        let mut check = IdCheck::new(&mut out, Some(&id));
+        check.write_all(b"<html>").unwrap();
+        assert!(check.finish(StatusCode::OK).is_err());
+        assert_eq!(out, b"<html>");
+
+        let mut out = Vec::new();
+        let mut check = IdCheck::new(&mut out, Some(&id));
+        check.write_all(&crx[..20]).unwrap();
+        check.finish(StatusCode::PARTIAL_CONTENT).unwrap();
+        assert_eq!(out, &crx[..20]);
+    }
+}
@@ -0,0 +1,161 @@
+// Dependencies
+use std::{fmt, io::{self, ErrorKind}};
+use reqwest::StatusCode;
+use crate::{updatecheck::{HashMismatch, NotServed}, VerifyError};
+
+/// Everything that can go wrong in this crate, returned by its public functions.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The CRX could not be read or converted.
+    #[error(transparent)]
+    Crx(#[from] CrxError),
+    /// A request could not be sent, or its response could not be read.
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    /// The server answered with a status that isn't a success.
+    #[error("request failed with status {0}")]
+    Status(StatusCode),
+    /// The server answered successfully, but without a body.
+    #[error("the server answered {0} without a body")]
+    EmptyResponse(StatusCode),
+    /// The store answered without a CRX, and the updatecheck says why.
+    #[error(transparent)]
+    NotServed(#[from] NotServed),
+    /// The signature, extension id or signing key of the CRX is wrong.
+    #[error(transparent)]
+    SignatureInvalid(#[from] VerifyError),
+    /// The CRX doesn't have the hash the updatecheck reported.
+    #[error(transparent)]
+    HashMismatch(#[from] HashMismatch),
+    /// Anything else, such as a failure to read or write a file.
+    #[error(transparent)]
+    Io(io::Error),
+}
+impl Error {
+    /// An [`Error::Io`] of `kind`.
+    pub(crate) fn new(kind: ErrorKind, error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        io::Error::new(kind, error).into()
+    }
+
+    /// An [`Error::Io`] of [`ErrorKind::Other`], or the matching variant if `error` is one of this crate's.
+    pub(crate) fn other(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        io::Error::other(error).into()
+    }
+
+    /// The closest [`ErrorKind`], e.g. for code that used to get an [`io::Error`].
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Crx(CrxError::TruncatedHeader) | Self::EmptyResponse(_) => ErrorKind::UnexpectedEof,
+            Self::Crx(_) | Self::SignatureInvalid(_) | Self::HashMismatch(_) => ErrorKind::InvalidData,
+            Self::Http(x) if x.is_timeout() => ErrorKind::TimedOut,
+            Self::Io(x) => x.kind(),
+            _ => ErrorKind::Other,
+        }
+    }
+}
+impl From<io::Error> for Error {
+    /// Unwraps errors of this crate that had to travel inside an [`io::Error`], e.g. out of a [`std::io::Write`].
+    fn from(value: io::Error) -> Self {
+        if !value.get_ref().is_some_and(|x| x.is::<Error>() || x.is::<CrxError>() || x.is::<reqwest::Error>() || x.is::<NotServed>() || x.is::<VerifyError>() || x.is::<HashMismatch>()) {
+            return Self::Io(value);
+        }
+        let inner = value.into_inner().unwrap();
+        let inner = match inner.downcast::<Error>() {
+            Ok(x) => return *x,
+            Err(x) => x,
+        };
+        let inner = match inner.downcast::<CrxError>() {
+            Ok(x) => return Self::Crx(*x),
+            Err(x) => x,
+        };
+        let inner = match inner.downcast::<reqwest::Error>() {
+            Ok(x) => return Self::Http(*x),
+            Err(x) => x,
+        };
+        let inner = match inner.downcast::<NotServed>() {
+            Ok(x) => return Self::NotServed(*x),
+            Err(x) => x,
+        };
+        let inner = match inner.downcast::<VerifyError>() {
+            Ok(x) => return Self::SignatureInvalid(*x),
+            Err(x) => x,
+        };
+        match inner.downcast::<HashMismatch>() {
+            Ok(x) => Self::HashMismatch(*x),
+            Err(x) => Self::Io(io::Error::other(x)),
+        }
+    }
+}
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        io::Error::from(value).into()
+    }
+}
+impl From<zip::result::ZipError> for Error {
+    fn from(value: zip::result::ZipError) -> Self {
+        io::Error::from(value).into()
+    }
+}
+impl From<Error> for io::Error {
+    /// Wraps `value`, so it can be passed through [`std::io::Write`] and turned back with `From`.
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Io(x) => x,
+            x => io::Error::new(x.kind(), x),
+        }
+    }
+}
+
+/// Why a CRX could not be read or converted, see [`Error::Crx`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrxError {
+    /// The input does not start with the `Cr24` magic number.
+    NotACrx,
+    /// The CRX version is not one this crate knows, or not the one that was expected.
+    UnsupportedVersion(u32),
+    /// The input ends before the header does.
+    TruncatedHeader,
+    /// The header claims to be larger than allowed, see [`crate::header::parse_header_with_limit`].
+    HeaderTooLarge(usize),
+    /// What follows the header is not a ZIP, see [`crate::ZIP_MAGIC`].
+    NotAZip,
+}
+impl fmt::Display for CrxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotACrx => write!(f, "input is not a crx file"),
+            Self::UnsupportedVersion(x) => write!(f, "invalid crx version: {}", x),
+            Self::TruncatedHeader => write!(f, "truncated crx header"),
+            Self::HeaderTooLarge(x) => write!(f, "crx header is too large: {} bytes", x),
+            Self::NotAZip => write!(f, "crx payload is not a zip, the crx may be truncated or corrupted"),
+        }
+    }
+}
+impl std::error::Error for CrxError {}
+impl From<CrxError> for io::Error {
+    fn from(value: CrxError) -> Self {
+        Error::Crx(value).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unwraps_errors_passed_through_io() {
+        let error: Error = io::Error::from(CrxError::TruncatedHeader).into();
+        assert!(matches!(error, Error::Crx(CrxError::TruncatedHeader)));
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        let mismatch = HashMismatch { app_id: "a".into(), expected: "00".into(), actual: "11".into() };
+        let error: Error = io::Error::from(Error::from(mismatch)).into();
+        assert!(matches!(error, Error::HashMismatch(x) if x.expected == "00"));
+
+        let verify = VerifyError::IdMismatch { expected: "a".into(), actual: "b".into() };
+        assert!(matches!(Error::from(io::Error::other(verify)), Error::SignatureInvalid(VerifyError::IdMismatch { .. })));
+        assert!(matches!(Error::other("status 500"), Error::Io(x) if x.to_string() == "status 500"));
+        assert_eq!(Error::from(io::Error::from(ErrorKind::NotFound)).kind(), ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,84 @@
+// Dependencies
+use std::{io::ErrorKind, path::Path};
+use crate::{analysis::{ExtensionKind, Package}, archive::{list_entries, read_manifest, EntryInfo}, crx_to_zip, extract::{unpack_to_dir, ExtractReport}, fs::write_atomic, sha256_hex, Error};
+
+/// A downloaded CRX, along with the id it was requested as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadedExtension {
+    id: String,
+    crx: Vec<u8>,
+}
+impl DownloadedExtension {
+    /// Wraps an already downloaded CRX.
+    pub fn new(id: impl Into<String>, crx: Vec<u8>) -> Self {
+        Self { id: id.into(), crx }
+    }
+
+    /// The extension id that was requested.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The raw CRX.
+    pub fn crx(&self) -> &[u8] {
+        &self.crx
+    }
+
+    /// Takes the raw CRX.
+    pub fn into_crx(self) -> Vec<u8> {
+        self.crx
+    }
+
+    /// The hex encoded SHA-256 of the CRX.
+    pub fn sha256(&self) -> String {
+        sha256_hex(&self.crx)
+    }
+
+    /// Atomically writes the CRX to `path`.
+    pub fn save_crx(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        write_atomic(path, &self.crx)
+    }
+
+    /// Converts the CRX to a ZIP.
+    pub fn to_zip(&self) -> Result<Vec<u8>, Error> {
+        crx_to_zip(self.crx.clone(), None)
+    }
+
+    /// Atomically writes the converted ZIP to `path`.
+    pub fn save_zip(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        write_atomic(path, &self.to_zip()?)
+    }
+
+    /// Unpacks the extension into `dir`, see [`unpack_to_dir`].
+    pub fn unpack_to_dir(&self, dir: impl AsRef<Path>) -> Result<ExtractReport, Error> {
+        unpack_to_dir(&self.crx, dir)
+    }
+
+    /// Loads the package for analysis, e.g. [`crate::analysis::check_architecture`].
+    pub fn package(&self) -> Result<Package, Error> {
+        Package::from_zip(&self.to_zip()?)
+    }
+
+    /// Reads `manifest.json` out of the package.
+    pub fn manifest(&self) -> Result<serde_json::Value, Error> {
+        read_manifest(&self.crx)
+    }
+
+    /// Lists the entries of the package, see [`list_entries`].
+    pub fn entries(&self) -> Result<Vec<EntryInfo>, Error> {
+        list_entries(&self.crx)
+    }
+
+    /// Whether this is an extension, theme or app, see [`ExtensionKind`].
+    pub fn kind(&self) -> Result<ExtensionKind, Error> {
+        Ok(ExtensionKind::from_manifest(&self.manifest()?))
+    }
+
+    /// The version declared in the manifest.
+    pub fn version(&self) -> Result<String, Error> {
+        self.manifest()?["version"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "manifest has no version"))
+    }
+}
@@ -0,0 +1,257 @@
+// Dependencies
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Cursor, ErrorKind},
+    path::{Component, Path, PathBuf},
+};
+use unicode_normalization::UnicodeNormalization;
+use crate::Error;
+
+/// Device names Windows reserves in every directory, regardless of extension.
+pub const RESERVED_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul",
+    "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+    "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Whether Windows reserves `name`, e.g. `con` or `aux.txt`.
+pub fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    RESERVED_NAMES.iter().any(|x| x.eq_ignore_ascii_case(stem))
+}
+
+/// What to do with entries that have a [reserved name](is_reserved_name) somewhere in their path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReservedNames {
+    /// Extract them as-is.
+    Keep,
+    /// Append `_` to the reserved part, so `con.txt` becomes `con_.txt`.
+    Rename,
+    /// Leave them out.
+    Skip,
+    /// Fail before anything is extracted.
+    Error,
+}
+impl Default for ReservedNames {
+    /// [`ReservedNames::Rename`] on Windows, where they can't be created, otherwise [`ReservedNames::Keep`].
+    fn default() -> Self {
+        if cfg!(windows) {
+            Self::Rename
+        } else {
+            Self::Keep
+        }
+    }
+}
+
+/// How a zip is extracted.
+/// 
+/// Entries renamed by any of the options are listed in [`ExtractReport::renamed`].
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    pub reserved_names: ReservedNames,
+    /// Write through `\\?\` prefixed paths on Windows, so paths longer than `MAX_PATH` work.
+    /// Does nothing elsewhere.
+    pub long_paths: bool,
+    /// Remove control characters from names, replacing names left empty with `_`.
+    pub strip_control_chars: bool,
+    /// Normalize names to Unicode NFC, so visually identical names map to the same file.
+    pub normalize_unicode: bool,
+    /// Treat names that only differ in case as the same file, suffixing later ones with `~1`, `~2`, ...
+    /// Defaults to `true` on Windows and macOS, whose filesystems are usually case-insensitive.
+    pub case_insensitive: bool,
+}
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            reserved_names: ReservedNames::default(),
+            long_paths: true,
+            strip_control_chars: true,
+            normalize_unicode: true,
+            case_insensitive: cfg!(any(windows, target_os = "macos")),
+        }
+    }
+}
+
+/// What was extracted, and any entries that didn't keep their name.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractReport {
+    /// Every file written, relative to the output directory.
+    pub files: Vec<PathBuf>,
+    /// `(entry name, path written to)` for entries that had to be renamed.
+    pub renamed: Vec<(String, PathBuf)>,
+    /// Entries that were left out.
+    pub skipped: Vec<String>,
+}
+
+/// Where an entry ends up.
+enum Target {
+    Write(PathBuf),
+    Skip,
+}
+
+/// Works out the relative path an entry is extracted to.
+fn target(name: &str, enclosed: &Path, options: &ExtractOptions) -> Result<Target, Error> {
+    let mut path = PathBuf::new();
+    for component in enclosed.components() {
+        let Component::Normal(part) = component else {
+            continue;
+        };
+        let mut part = part.to_string_lossy().into_owned();
+        if options.strip_control_chars {
+            part.retain(|x| !x.is_control());
+            if part.is_empty() {
+                part.push('_');
+            }
+        }
+        if options.normalize_unicode {
+            part = part.nfc().collect();
+        }
+        // Cleaning up may have turned it into something enclosed_name would have rejected, e.g. `\x01..`
+        if part == "." || part == ".." || part.contains(std::path::is_separator) {
+            return Err(Error::new(ErrorKind::InvalidData, format!("zip entry escapes the output directory: {}", name)));
+        }
+        if !is_reserved_name(&part) {
+            path.push(part);
+            continue;
+        }
+
+        match options.reserved_names {
+            ReservedNames::Keep => path.push(part),
+            ReservedNames::Rename => {
+                let renamed = match part.split_once('.') {
+                    Some((stem, extension)) => format!("{}_.{}", stem, extension),
+                    None => format!("{}_", part),
+                };
+                path.push(renamed);
+            },
+            ReservedNames::Skip => return Ok(Target::Skip),
+            ReservedNames::Error => return Err(Error::new(ErrorKind::InvalidData, format!("zip entry has a reserved name: {}", name))),
+        }
+    }
+    Ok(Target::Write(path))
+}
+
+/// Suffixes the file name of `path` with `~1`, `~2`, ... until its lowercase form is not in `taken`.
+fn deduplicate(path: PathBuf, taken: &mut HashSet<String>) -> PathBuf {
+    let key = |x: &Path| x.to_string_lossy().to_lowercase();
+    if taken.insert(key(&path)) {
+        return path;
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let extension = path.extension().map(|x| format!(".{}", x.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|i| path.with_file_name(format!("{}~{}{}", stem, i, extension)))
+        .find(|x| taken.insert(key(x)))
+        .unwrap()
+}
+
+/// Turns `path` into one Windows accepts past `MAX_PATH`, e.g. `\\?\C:\...` or `\\?\UNC\server\share\...`.
+#[cfg(windows)]
+fn long_path(path: &Path) -> Result<PathBuf, Error> {
+    let path = std::path::absolute(path)?;
+    let raw = path.as_os_str().to_string_lossy();
+    if raw.starts_with(r"\\?\") {
+        return Ok(path);
+    }
+    Ok(match raw.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!(r"\\?\UNC\{}", unc)),
+        None => PathBuf::from(format!(r"\\?\{}", raw)),
+    })
+}
+
+/// Extracts a zip, such as the output of [`crate::crx_to_zip`], into `dir`.
+/// 
+/// Entries that would escape `dir` are rejected. Every entry's target is worked out before anything is written,
+/// so a [`ReservedNames::Error`] fails without leaving a half extracted directory behind.
+pub fn extract_zip(zip: &[u8], dir: impl AsRef<Path>, options: &ExtractOptions) -> Result<ExtractReport, Error> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(zip))?;
+
+    // Work out where everything goes
+    let mut report = ExtractReport::default();
+    let mut targets = Vec::with_capacity(archive.len());
+    let mut taken = HashSet::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        let name = entry.name().to_string();
+        let enclosed = entry
+            .enclosed_name()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("zip entry escapes the output directory: {}", name)))?;
+
+        match target(&name, &enclosed, options)? {
+            Target::Write(mut path) => {
+                if options.case_insensitive && !entry.is_dir() {
+                    path = deduplicate(path, &mut taken);
+                }
+                if path != enclosed {
+                    report.renamed.push((name, path.clone()));
+                }
+                targets.push((i, entry.is_dir(), path));
+            },
+            Target::Skip => report.skipped.push(name),
+        }
+    }
+
+    // Write them out
+    #[cfg(windows)]
+    let dir = if options.long_paths { long_path(dir.as_ref())? } else { dir.as_ref().to_path_buf() };
+    #[cfg(not(windows))]
+    let dir = dir.as_ref().to_path_buf();
+    for (i, is_dir, path) in targets {
+        let out = dir.join(&path);
+        if is_dir {
+            fs::create_dir_all(&out)?;
+            continue;
+        }
+
+        if let Some(parent) = out.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut archive.by_index(i)?, &mut File::create(&out)?)?;
+        report.files.push(path);
+    }
+
+    // Done
+    Ok(report)
+}
+
+/// Unpacks a CRX or ZIP into `dir`, ready to be loaded as an unpacked extension.
+/// 
+/// The CRX is not copied, see [`crate::crx_to_zip_slice`]. Entries are extracted with the default [`ExtractOptions`],
+/// so paths escaping `dir` are rejected, use [`extract_zip`] for other options.
+pub fn unpack_to_dir(crx_or_zip: &[u8], dir: impl AsRef<Path>) -> Result<ExtractReport, Error> {
+    extract_zip(crate::crx_to_zip_slice(crx_or_zip)?, dir, &ExtractOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// A zip with an empty file for each of `names`.
+    fn zip(names: &[&str]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for name in names {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn stripped_names_stay_enclosed() {
+        let root = std::env::temp_dir().join(format!("crx-dl-extract-{}", std::process::id()));
+        let dir = root.join("out");
+        let zip = zip(&["a/\x01../\x01../escaped.txt"]);
+
+        assert!(extract_zip(&zip, &dir, &ExtractOptions::default()).is_err());
+        assert!(unpack_to_dir(&zip, &dir).is_err());
+        assert!(!root.join("escaped.txt").exists());
+
+        // Other control characters are still just removed
+        let report = unpack_to_dir(&self::zip(&["a\x01b/c.txt"]), &dir).unwrap();
+        assert_eq!(report.files, [PathBuf::from("ab/c.txt")]);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+// Dependencies
+use std::{io::ErrorKind, path::{Path, PathBuf}};
+use crate::{fs::write_atomic, is_extension_id, ChromeCRXQuery, DownloadedExtension, Downloader, Error};
+
+/// Builds the default query for `id`, rejecting anything that isn't an extension id.
+fn query(id: &str) -> Result<ChromeCRXQuery<'_>, Error> {
+    if !is_extension_id(id) {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("not an extension id: {}", id)));
+    }
+    Ok(ChromeCRXQuery { x: id, ..Default::default() })
+}
+
+/// Downloads the extension `id` from the Chrome Web Store, with the default query and [`Downloader`].
+/// 
+/// For a blocking version, use [`fetch_blocking`].
+pub async fn fetch(id: &str) -> Result<DownloadedExtension, Error> {
+    let crx = Downloader::default().download(&query(id)?).await?;
+    Ok(DownloadedExtension::new(id, crx))
+}
+
+/// Downloads the extension `id` from the Chrome Web Store, with the default query and [`Downloader`].
+/// 
+/// For a async version, use [`fetch`].
+pub fn fetch_blocking(id: &str) -> Result<DownloadedExtension, Error> {
+    let crx = Downloader::default().download_blocking(&query(id)?)?;
+    Ok(DownloadedExtension::new(id, crx))
+}
+
+/// Downloads the extension `id` into `dir` as `<id>.crx`, returning the path it was saved to.
+/// 
+/// For a blocking version, use [`fetch_to_blocking`].
+pub async fn fetch_to(id: &str, dir: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    let path = dir.as_ref().join(format!("{}.crx", id));
+    write_atomic(&path, fetch(id).await?.crx())?;
+    Ok(path)
+}
+
+/// Downloads the extension `id` into `dir` as `<id>.crx`, returning the path it was saved to.
+/// 
+/// For a async version, use [`fetch_to`].
+pub fn fetch_to_blocking(id: &str, dir: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    let path = dir.as_ref().join(format!("{}.crx", id));
+    write_atomic(&path, fetch_blocking(id)?.crx())?;
+    Ok(path)
+}
@@ -0,0 +1,146 @@
+// Dependencies
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File},
+    future::Future,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use crate::Error;
+
+/// Makes temporary file names unique within the process.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary file next to `path`, hidden and suffixed with `.tmp`.
+fn temp_path(path: &Path) -> Result<PathBuf, Error> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "path has no file name"))?;
+    let unique = format!(
+        ".{}.{}-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    Ok(path.with_file_name(unique))
+}
+
+/// Writes to a temporary file in the same directory, then renames it over `path` once `write` succeeds.
+/// 
+/// Readers only ever see the old file or the complete new one, never a truncated one.
+/// The temporary file is removed if anything fails.
+pub fn write_atomic_with(path: impl AsRef<Path>, write: impl FnOnce(&mut File) -> Result<(), Error>) -> Result<(), Error> {
+    let path = path.as_ref();
+    let temp = temp_path(path)?;
+
+    let result = File::create(&temp)
+        .map_err(Error::from)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.flush()?;
+            Ok(file.sync_all()?)
+        })
+        .and_then(|_| Ok(fs::rename(&temp, path)?));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// [`write_atomic_with`] for async writes, which get the file and hand it back once done.
+pub(crate) async fn write_atomic_with_async<F: Future<Output = Result<File, Error>>>(path: impl AsRef<Path>, write: impl FnOnce(File) -> F) -> Result<(), Error> {
+    let path = path.as_ref();
+    let temp = temp_path(path)?;
+
+    let result = async {
+        let mut file = write(File::create(&temp)?).await?;
+        file.flush()?;
+        file.sync_all()?;
+        Ok(fs::rename(&temp, path)?)
+    }.await;
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Atomically replaces the contents of `path` with `data`, see [`write_atomic_with`].
+pub fn write_atomic(path: impl AsRef<Path>, data: &[u8]) -> Result<(), Error> {
+    write_atomic_with(path, |file| Ok(file.write_all(data)?))
+}
+
+/// The name of the combined checksum file, see [`Sha256Sums`].
+pub const SHA256SUMS_NAME: &str = "SHA256SUMS";
+
+/// The sidecar path for `path`, e.g. `a.crx.sha256` for `a.crx`.
+pub fn sha256_sidecar_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".sha256");
+    path.with_file_name(name)
+}
+
+/// Writes a `.sha256` sidecar next to `path`, in the format `sha256sum -c` understands.
+pub fn write_sha256_sidecar(path: impl AsRef<Path>, data: &[u8]) -> Result<(), Error> {
+    let path = path.as_ref();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let line = format!("{}  {}\n", crate::sha256_hex(data), name);
+    write_atomic(sha256_sidecar_path(path), line.as_bytes())
+}
+
+/// Collects checksums of many files into a single `SHA256SUMS` file.
+/// 
+/// Displays as its contents, one `<sha256>  <path>` line per file, sorted by path.
+#[derive(Debug, Clone, Default)]
+pub struct Sha256Sums {
+    /// The hex encoded sha256 of each path, relative to where the file is written.
+    pub entries: BTreeMap<String, String>,
+}
+impl Sha256Sums {
+    /// Records the checksum of `data`, stored at `path`, replacing any earlier one.
+    pub fn add(&mut self, path: &str, data: &[u8]) {
+        self.entries.insert(path.to_string(), crate::sha256_hex(data));
+    }
+
+    /// Writes [`SHA256SUMS_NAME`] into `dir`, in the format `sha256sum -c` understands.
+    pub fn write(&self, dir: impl AsRef<Path>) -> Result<(), Error> {
+        write_atomic(dir.as_ref().join(SHA256SUMS_NAME), self.to_string().as_bytes())
+    }
+}
+impl fmt::Display for Sha256Sums {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, sha256) in &self.entries {
+            writeln!(f, "{}  {}", sha256, path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256sums_pass_sha256sum_check() {
+        let dir = std::env::temp_dir().join(format!("crx-dl-sums-{}", std::process::id()));
+        fs::create_dir_all(dir.join("a")).unwrap();
+        let mut sums = Sha256Sums::default();
+        for (path, data) in [("b.crx", &b"old"[..]), ("a/c.zip", b"c"), ("b.crx", b"new")] {
+            fs::write(dir.join(path), data).unwrap();
+            sums.add(path, data);
+        }
+        sums.write(&dir).unwrap();
+        assert_eq!(fs::read_to_string(dir.join(SHA256SUMS_NAME)).unwrap(), format!(
+            "{}  a/c.zip\n{}  b.crx\n",
+            crate::sha256_hex(b"c"), crate::sha256_hex(b"new"),
+        ));
+
+        // Only where coreutils is around
+        if let Ok(output) = std::process::Command::new("sha256sum").arg("-c").arg(SHA256SUMS_NAME).current_dir(&dir).output() {
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+// Dependencies
+use std::io::ErrorKind;
+use crate::{signature::ProofAlgorithm, CrxError, Error};
+
+/// The version of a CRX file, the 4 bytes after the magic number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display)]
+pub enum CrxVersion {
+    /// A public key and a SHA-1 signature, deprecated since Chrome 64.
+    #[strum(serialize="2")]
+    V2,
+    /// A protobuf header, see [`CrxFileHeader`].
+    #[strum(serialize="3")]
+    V3,
+}
+impl CrxVersion {
+    /// The version as written in the file.
+    pub fn number(self) -> u32 {
+        match self {
+            Self::V2 => 2,
+            Self::V3 => 3,
+        }
+    }
+}
+impl TryFrom<u32> for CrxVersion {
+    type Error = CrxError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            2 => Ok(Self::V2),
+            3 => Ok(Self::V3),
+            _ => Err(CrxError::UnsupportedVersion(value)),
+        }
+    }
+}
+
+/// The part of a CRX header every version has, see [`parse_header`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrxHeader {
+    pub version: CrxVersion,
+    /// The offset the archive starts at, right after the header.
+    pub archive_offset: usize,
+}
+
+/// The largest header [`parse_header`] accepts. Real headers are a few kilobytes at most.
+pub const DEFAULT_MAX_HEADER_SIZE: usize = 16 * 1024 * 1024;
+
+/// Reads the magic number, version and header lengths of a CRX, without touching what follows.
+/// 
+/// Only the header needs to be present. Parse the rest with [`Crx3Header::parse`] or [`crate::signature::extract_signature`].
+/// Headers larger than [`DEFAULT_MAX_HEADER_SIZE`] are rejected, see [`parse_header_with_limit`].
+pub fn parse_header(crx: &[u8]) -> Result<CrxHeader, Error> {
+    parse_header_with_limit(crx, DEFAULT_MAX_HEADER_SIZE)
+}
+
+/// Like [`parse_header`], rejecting headers larger than `max_header_size` bytes with [`CrxError::HeaderTooLarge`].
+/// 
+/// The declared lengths are never trusted beyond the size of `crx`, whatever the limit.
+pub fn parse_header_with_limit(crx: &[u8], max_header_size: usize) -> Result<CrxHeader, Error> {
+    if crx.get(..4) != Some(b"Cr24") {
+        return Err(CrxError::NotACrx.into());
+    }
+
+    // The lengths of the header parts, depending on the version
+    let read_u32 = |offset: usize| crx
+        .get(offset..offset + 4)
+        .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+        .ok_or(CrxError::TruncatedHeader);
+    let version = CrxVersion::try_from(read_u32(4)?)?;
+    let archive_offset = match version {
+        CrxVersion::V2 => (read_u32(8)? as usize).saturating_add(read_u32(12)? as usize).saturating_add(16),
+        CrxVersion::V3 => (read_u32(8)? as usize).saturating_add(12),
+    };
+    if archive_offset > max_header_size {
+        return Err(CrxError::HeaderTooLarge(archive_offset).into());
+    }
+    if archive_offset > crx.len() {
+        return Err(CrxError::TruncatedHeader.into());
+    }
+    Ok(CrxHeader { version, archive_offset })
+}
+
+/// A field that is not part of the known schema, kept as-is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownField {
+    /// The field number.
+    pub number: u64,
+    /// The protobuf wire type, e.g. 0 for varints or 2 for length delimited data.
+    pub wire_type: u8,
+    /// The raw value, without the length prefix for length delimited fields.
+    pub value: Vec<u8>,
+}
+impl UnknownField {
+    fn new(number: u64, wire_type: u8, value: &[u8]) -> Self {
+        Self { number, wire_type, value: value.to_vec() }
+    }
+}
+
+/// A public key and the signature made with it.
+///
+/// See <https://source.chromium.org/chromium/chromium/src/+/main:components/crx_file/crx3.proto>.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AsymmetricKeyProof {
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+    /// Fields not covered by the schema above.
+    pub unknown_fields: Vec<UnknownField>,
+}
+impl AsymmetricKeyProof {
+    /// Decodes the protobuf message.
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut proof = Self::default();
+        for field in Fields(buf) {
+            match field? {
+                (1, LEN, x) => proof.public_key = x.to_vec(),
+                (2, LEN, x) => proof.signature = x.to_vec(),
+                (number, wire_type, x) => proof.unknown_fields.push(UnknownField::new(number, wire_type, x)),
+            }
+        }
+        Ok(proof)
+    }
+}
+
+/// The protobuf header of a CRX3 file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrxFileHeader {
+    pub sha256_with_rsa: Vec<AsymmetricKeyProof>,
+    pub sha256_with_ecdsa: Vec<AsymmetricKeyProof>,
+    /// The serialized `SignedData` message, which is covered by the signatures.
+    pub signed_header_data: Option<Vec<u8>>,
+    /// Fields not covered by the schema above, such as ones added to the format after this crate was written.
+    pub unknown_fields: Vec<UnknownField>,
+}
+impl CrxFileHeader {
+    /// Decodes the protobuf message.
+    pub fn decode(buf: &[u8]) -> Result<Self, Error> {
+        let mut header = Self::default();
+        for field in Fields(buf) {
+            match field? {
+                (2, LEN, x) => header.sha256_with_rsa.push(AsymmetricKeyProof::decode(x)?),
+                (3, LEN, x) => header.sha256_with_ecdsa.push(AsymmetricKeyProof::decode(x)?),
+                (10000, LEN, x) => header.signed_header_data = Some(x.to_vec()),
+                (number, wire_type, x) => header.unknown_fields.push(UnknownField::new(number, wire_type, x)),
+            }
+        }
+        Ok(header)
+    }
+
+    /// The crx id declared in `signed_header_data`, the first 16 bytes of the SHA-256 of the extension's public key.
+    pub fn crx_id(&self) -> Result<Option<Vec<u8>>, Error> {
+        let Some(signed_header_data) = &self.signed_header_data else {
+            return Ok(None);
+        };
+        for field in Fields(signed_header_data) {
+            if let (1, LEN, x) = field? {
+                return Ok(Some(x.to_vec()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Every proof along with its algorithm, RSA first.
+    pub fn proofs(&self) -> impl Iterator<Item = (ProofAlgorithm, &AsymmetricKeyProof)> {
+        self.sha256_with_rsa
+            .iter()
+            .map(|x| (ProofAlgorithm::Sha256WithRsa, x))
+            .chain(self.sha256_with_ecdsa.iter().map(|x| (ProofAlgorithm::Sha256WithEcdsa, x)))
+    }
+
+    /// The public key the declared crx id was derived from, if any.
+    pub fn declared_key(&self) -> Result<Option<&[u8]>, Error> {
+        use sha2::{Digest, Sha256};
+        let Some(crx_id) = self.crx_id()? else {
+            return Ok(None);
+        };
+        Ok(self
+            .proofs()
+            .map(|(_, x)| x.public_key.as_slice())
+            .find(|x| Sha256::digest(x)[..16] == crx_id))
+    }
+
+    /// The public key the crx id was derived from, the key of the first proof if none match.
+    pub fn public_key(&self) -> Result<Option<&[u8]>, Error> {
+        Ok(self.declared_key()?.or_else(|| self.proofs().next().map(|(_, x)| x.public_key.as_slice())))
+    }
+}
+
+/// The header of a CRX3 file, along with where it ends.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Crx3Header {
+    pub file_header: CrxFileHeader,
+    /// The crx id declared in `signed_header_data`, see [`CrxFileHeader::crx_id`].
+    pub crx_id: Option<[u8; 16]>,
+    /// The offset the archive starts at.
+    pub archive_offset: usize,
+}
+impl Crx3Header {
+    /// Parses the header of `crx`, which must be a CRX3 file. Only the header needs to be present.
+    pub fn parse(crx: &[u8]) -> Result<Self, Error> {
+        let CrxHeader { version, archive_offset } = parse_header(crx)?;
+        if version != CrxVersion::V3 {
+            return Err(CrxError::UnsupportedVersion(version.number()).into());
+        }
+        let file_header = CrxFileHeader::decode(&crx[12..archive_offset])?;
+
+        let crx_id = file_header
+            .crx_id()?
+            .map(|x| x.try_into().map_err(|_| Error::new(ErrorKind::InvalidData, "crx id is not 16 bytes")))
+            .transpose()?;
+        Ok(Self { file_header, crx_id, archive_offset })
+    }
+
+    /// The declared crx id as an extension id, e.g. `pmjhpjmeehbpchodikkdncnhgmejnjai`.
+    pub fn extension_id(&self) -> Option<String> {
+        self.crx_id.map(|x| crate::encode_extension_id(&x))
+    }
+}
+
+/// The wire type of length delimited fields.
+const LEN: u8 = 2;
+
+/// Iterates over the `(field number, wire type, payload)` of each field in a protobuf message.
+///
+/// For length delimited fields the payload excludes the length prefix, otherwise it is the raw encoded value.
+struct Fields<'a>(&'a [u8]);
+impl<'a> Fields<'a> {
+    /// Reads a varint from the front of the buffer.
+    fn varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0u64;
+        for (i, byte) in self.0.iter().enumerate().take(10) {
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                self.0 = &self.0[i + 1..];
+                return Ok(value);
+            }
+        }
+        Err(truncated())
+    }
+
+    /// Takes `len` bytes from the front of the buffer.
+    fn take(&mut self, len: u64) -> Result<&'a [u8], Error> {
+        let len = usize::try_from(len).map_err(|_| truncated())?;
+        if len > self.0.len() {
+            return Err(truncated());
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    /// Reads the next field.
+    fn field(&mut self) -> Result<(u64, u8, &'a [u8]), Error> {
+        let key = self.varint()?;
+        let wire_type = (key & 7) as u8;
+        let payload = match wire_type {
+            0 => {
+                let start = self.0;
+                self.varint()?;
+                &start[..start.len() - self.0.len()]
+            },
+            1 => self.take(8)?,
+            LEN => {
+                let len = self.varint()?;
+                self.take(len)?
+            },
+            5 => self.take(4)?,
+            _ => return Err(Error::new(ErrorKind::InvalidData, "unsupported protobuf wire type")),
+        };
+        Ok((key >> 3, wire_type, payload))
+    }
+}
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u64, u8, &'a [u8]), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            self.0 = &[];
+        }
+        Some(field)
+    }
+}
+
+/// The error returned when a message ends mid-field.
+fn truncated() -> Error {
+    Error::new(ErrorKind::UnexpectedEof, "truncated protobuf message")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_key_falls_back_to_first_proof() {
+        let header = Crx3Header::parse(&crate::mock::crx3(b"PK\x05\x06")).unwrap();
+        assert_eq!((header.crx_id, header.archive_offset), (None, 42));
+        assert_eq!(header.file_header.declared_key().unwrap(), None);
+        assert_eq!(header.file_header.public_key().unwrap(), Some(&[4u8; 16][..]));
+        assert_eq!(header.file_header.proofs().count(), 1);
+    }
+}
@@ -1,6 +1,64 @@
 // Dependencies
-use std::io::{Cursor, BufReader, Read, SeekFrom, Seek, ErrorKind, Error};
+use std::{fmt, io::{Cursor, BufReader, Read, SeekFrom, Seek, ErrorKind}, path::Path};
 use base64::{engine::general_purpose, Engine as _};
+use futures_util::TryStreamExt;
+
+// Modules
+pub mod admin_report;
+mod amo;
+pub use amo::{AmoFile, FirefoxAMOQuery, AMO_API_URL};
+pub mod analysis;
+pub mod archive;
+pub mod audit;
+pub mod batch;
+mod client;
+pub use client::{shared_blocking_client, shared_client};
+mod error;
+pub use error::{CrxError, Error};
+pub mod content_verification;
+mod custom_update;
+pub use custom_update::CustomUpdateQuery;
+mod downloader;
+pub use downloader::{Conditional, DownloadResponse, Downloader, DownloaderOptions, Progress, ProgressCallback, Validators, validators_path};
+mod extension;
+pub use extension::DownloadedExtension;
+pub mod extract;
+mod facade;
+pub use facade::{fetch, fetch_blocking, fetch_to, fetch_to_blocking};
+pub mod fs;
+mod matrix;
+pub use matrix::{Platform, PlatformPackage};
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "mmap")]
+pub use mmap::{crx_to_zip_mmap, MappedZip};
+pub mod header;
+pub use header::CrxVersion;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+mod opera;
+pub use opera::{opera_crx_to_zip, OperaCRXQuery, OPERA_ADDONS_URL};
+pub mod prelude;
+mod preset;
+pub use preset::{Preset, PRESET_CHROME_VERSION};
+mod probe;
+pub use probe::CrxMetadata;
+mod quirks;
+pub use quirks::{crx_to_zip_tolerant, Quirk, QUIRK_SEARCH_LIMIT};
+mod rate_limit;
+pub use rate_limit::RateLimit;
+mod retry;
+pub use retry::RetryPolicy;
+pub mod signature;
+pub mod sink;
+mod stream;
+pub use stream::{crx_to_zip_file, crx_to_zip_stream, CrxToZipWriter};
+pub mod updatecheck;
+mod user_agent;
+mod verify;
+pub use verify::{crx_extension_id, verify_crx, verify_crx2, verify_crx3, verify_dir, verify_extension_id, VerifiedCrx, VerifyDirEntry, VerifyError, VerifyPolicy, VerifyReport};
+mod webstore;
+pub use webstore::{parse_webstore_metadata, SearchResult, WebStore, WebStoreMetadata, CHROME_WEB_STORE_URL};
 
 /// Possible product ids.
 #[derive(Debug, Clone, strum::Display, strum::EnumString)]
@@ -12,7 +70,7 @@ pub enum ProductId {
 }
 
 /// Types of operating systems.
-#[derive(Debug, Clone, strum::Display, strum::EnumString)]
+#[derive(Debug, Clone, PartialEq, Eq, strum::Display, strum::EnumString, strum::EnumIter)]
 pub enum OperatingSystem {
     #[strum(serialize="win")]
     Windows,
@@ -29,7 +87,7 @@ pub enum OperatingSystem {
 }
 
 /// Types of architecture.
-#[derive(Debug, Clone, strum::Display, strum::EnumString)]
+#[derive(Debug, Clone, PartialEq, Eq, strum::Display, strum::EnumString, strum::EnumIter)]
 pub enum Architecture {
     #[strum(serialize="arm")]
     ARM,
@@ -39,8 +97,105 @@ pub enum Architecture {
     AMD64,
 }
 
+/// Whether `id` looks like an extension id, 32 characters from `a` to `p`.
+pub fn is_extension_id(id: &str) -> bool {
+    id.len() == 32 && id.bytes().all(|x| (b'a'..=b'p').contains(&x))
+}
+
+/// Derives an extension id from `data`, the same way Chrome does.
+/// 
+/// The first 16 bytes of the SHA-256 are hex encoded, with each digit mapped from `0-f` to `a-p`.
+pub fn extension_id_from_bytes(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    encode_extension_id(&Sha256::digest(data)[..16])
+}
+
+/// The id of the extension signed with `der`, a DER encoded `SubjectPublicKeyInfo`.
+/// 
+/// This is the key in the CRX header, or the `key` field of a manifest once base64 decoded.
+pub fn extension_id_from_public_key(der: &[u8]) -> String {
+    extension_id_from_bytes(der)
+}
+
+/// Hex encodes `bytes`, with each digit mapped from `0-f` to `a-p`.
+pub(crate) fn encode_extension_id(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .flat_map(|x| [x >> 4, x & 0xf])
+        .map(|x| (b'a' + x) as char)
+        .collect()
+}
+
+/// The id Chrome gives an unpacked extension loaded from `path`.
+/// 
+/// Chrome hashes the absolute path with symlinks resolved, so `path` should already be in that form.
+/// On Windows the path is hashed as UTF-16, with the drive letter upper-cased.
+pub fn unpacked_extension_id(path: &std::path::Path) -> String {
+    #[cfg(windows)]
+    let bytes: Vec<u8> = {
+        use std::os::windows::ffi::OsStrExt;
+        let mut wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+        if let [drive @ 0x61..=0x7a, 0x3a, ..] = wide.as_mut_slice() {
+            *drive -= 0x20;
+        }
+        wide.iter().flat_map(|x| x.to_le_bytes()).collect()
+    };
+    #[cfg(not(windows))]
+    let bytes = path.as_os_str().as_encoded_bytes().to_vec();
+
+    extension_id_from_bytes(&bytes)
+}
+
+/// The default update endpoint used by Chrome.
+pub const CHROME_UPDATE_URL: &str = "https://clients2.google.com/service/update2/crx";
+/// The update endpoint of the Microsoft Edge Add-ons store, see [`Preset::Edge`] for the rest of its parameters.
+pub const EDGE_UPDATE_URL: &str = "https://edge.microsoft.com/extensionwebstorebase/v1/crx";
+
+/// The `x` query parameter, describing a single extension to the update server.
+/// 
+/// Formatted as its own url encoded query, e.g. `id=<id>&v=1.0&installsource=ondemand&uc`.
+/// The values are percent-encoded, so one containing `&` or `=` can't add parameters of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XParam<'a> {
+    pub id: &'a str,
+    pub installsource: Option<&'a str>,
+    /// Sent as `v`.
+    pub version: Option<&'a str>,
+    pub uc: bool,
+}
+impl fmt::Display for XParam<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "id={}", url_encode(self.id))?;
+        if let Some(version) = self.version {
+            write!(f, "&v={}", url_encode(version))?;
+        }
+        if let Some(installsource) = self.installsource {
+            write!(f, "&installsource={}", url_encode(installsource))?;
+        }
+        if self.uc {
+            f.write_str("&uc")?;
+        }
+        Ok(())
+    }
+}
+
+/// Percent-encodes a query value, keeping only the unreserved characters.
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|x| match x {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (x as char).to_string(),
+            _ => format!("%{:02X}", x),
+        })
+        .collect()
+}
+
 /// The query parameters sent to <https://clients2.google.com/service/update2/crx> for Chrome.
+#[derive(Clone)]
 pub struct ChromeCRXQuery<'a> {
+    /// The update endpoint the query is sent to, [`CHROME_UPDATE_URL`] by default.
+    /// Useful for pointing at a caching proxy or a mock server.
+    pub endpoint: &'a str,
     pub response: &'a str,
     pub os: OperatingSystem,
     pub arch: Architecture,
@@ -53,13 +208,32 @@ pub struct ChromeCRXQuery<'a> {
     /// As of July, the Chrome Web Store sends 204 responses to user agents when their
     /// Chrome/Chromium version is older than version 31.0.1609.0
     pub prodversion: &'a str,
+    /// The package formats accepted, `crx2,crx3` by default.
+    /// Themes and apps are served with the same parameters as extensions, see [`analysis::ExtensionKind`].
     pub acceptformat: &'a str,
-    pub x: &'a str
+    /// The extension id.
+    pub x: &'a str,
+    /// Where the install came from, e.g. `ondemand`, sent as part of `x`.
+    pub installsource: Option<&'a str>,
+    /// The version already installed, sent as part of `x`. Only newer versions are offered.
+    pub version: Option<&'a str>,
+    /// Whether to ask for an update check, sent as part of `x`. Chrome always does.
+    pub uc: bool,
+    /// The `User-Agent` header to send, reqwest's default if `None`.
+    pub user_agent: Option<&'a str>,
+    /// Without a `user_agent`, send one matching `os`, `arch` and `prodversion`, see [`ChromeCRXQuery::chrome_user_agent`].
+    pub synthesize_user_agent: bool,
+    /// Sent as the `Cookie` header, e.g. the session of a Google account that may see unlisted or domain restricted items.
+    pub cookie: Option<&'a str>,
+    /// An OAuth 2.0 access token, sent as `Authorization: Bearer <token>`.
+    pub access_token: Option<&'a str>,
+    /// Whether to check the downloaded CRX is of `x`, see [`verify_extension_id`]. Only applies to CRX responses.
+    pub verify_id: bool,
 }
 impl ChromeCRXQuery<'_> {
     /// Converts to a format where it can be used by reqwest.
     pub fn to_vec(&self) -> Vec<(String, String)> {
-        vec![
+        [
             ("response", self.response),
             ("os", &self.os.to_string()),
             ("arch", &self.arch.to_string()),
@@ -69,46 +243,235 @@ impl ChromeCRXQuery<'_> {
             ("prodchannel", self.prodchannel),
             ("prodversion", self.prodversion),
             ("acceptformat", self.acceptformat),
-            ("x", &format!("id={}&uc", self.x))
+            ("x", &self.x_param().to_string())
         ]
         .iter()
         .map(|x| (x.0.to_string(), x.1.to_string()))
         .collect()
     }
 
-    /// Downloads the extension.
+    /// The `x` parameter, made from `x`, `installsource`, `version` and `uc`.
+    pub fn x_param(&self) -> XParam<'_> {
+        XParam { id: self.x, installsource: self.installsource, version: self.version, uc: self.uc }
+    }
+
+    /// The headers sent with the query: the `User-Agent` and any credentials.
+    /// 
+    /// Credentials are dropped by reqwest when a redirect leads to another host, so they never reach the CRX download itself.
+    pub fn headers(&self) -> Vec<(reqwest::header::HeaderName, String)> {
+        let mut headers = Vec::new();
+        if let Some(user_agent) = self.effective_user_agent() {
+            headers.push((reqwest::header::USER_AGENT, user_agent.into_owned()));
+        }
+        if let Some(cookie) = self.cookie {
+            headers.push((reqwest::header::COOKIE, cookie.to_string()));
+        }
+        if let Some(access_token) = self.access_token {
+            headers.push((reqwest::header::AUTHORIZATION, format!("Bearer {}", access_token)));
+        }
+        headers
+    }
+
+    /// Builds the request for this query, on `client`.
+    pub fn request(&self, client: &reqwest::Client) -> reqwest::RequestBuilder {
+        let mut request = client
+            .get(self.endpoint)
+            .query(&self.to_vec());
+        for (name, value) in self.headers() {
+            request = request.header(name, value);
+        }
+        request
+    }
+
+    /// Builds the request for this query, on `client`.
+    pub fn request_blocking(&self, client: &reqwest::blocking::Client) -> reqwest::blocking::RequestBuilder {
+        let mut request = client
+            .get(self.endpoint)
+            .query(&self.to_vec());
+        for (name, value) in self.headers() {
+            request = request.header(name, value);
+        }
+        request
+    }
+
+    /// Downloads the extension, with the [`shared_client`].
     /// 
+    /// If the store doesn't answer with a CRX, it is asked for an updatecheck to find out why.
+    /// Should it still offer a CRX, that is downloaded instead, otherwise the reason is returned as a [`updatecheck::NotServed`].
+    /// A CRX of another extension fails with [`VerifyError::IdMismatch`], unless `verify_id` is unset.
     /// For a blocking version, use [`download_blocking`].
-    pub async fn download(&self) -> Result<Vec<u8>, reqwest::Error> {
+    pub async fn download(&self) -> Result<Vec<u8>, Error> {
+        self.download_with_client(shared_client()).await
+    }
+
+    /// Downloads the extension, with the [`shared_blocking_client`].
+    /// 
+    /// If the store doesn't answer with a CRX, it is asked for an updatecheck to find out why.
+    /// Should it still offer a CRX, that is downloaded instead, otherwise the reason is returned as a [`updatecheck::NotServed`].
+    /// A CRX of another extension fails with [`VerifyError::IdMismatch`], unless `verify_id` is unset.
+    /// For a async version, use [`download`].
+    pub fn download_blocking(&self) -> Result<Vec<u8>, Error> {
+        self.download_with_client_blocking(shared_blocking_client())
+    }
+
+    /// Downloads the extension on `client`, reusing its connection pool, default headers, proxy and TLS settings.
+    /// See [`ChromeCRXQuery::download`] for how unserved extensions are handled.
+    /// 
+    /// For a blocking version, use [`download_with_client_blocking`].
+    pub async fn download_with_client(&self, client: &reqwest::Client) -> Result<Vec<u8>, Error> {
+        let response = self.request(client).send().await?;
+        let status = response.status();
+        let crx = response.bytes().await?.to_vec();
+        if self.response != "redirect" {
+            return Ok(crx);
+        }
+        let crx = if is_crx_response(status, &crx) {
+            crx
+        } else {
+            self.download_unserved(client).await?
+        };
+        self.check_id(crx)
+    }
+
+    /// Downloads the extension on `client`, reusing its connection pool, default headers, proxy and TLS settings.
+    /// See [`ChromeCRXQuery::download_blocking`] for how unserved extensions are handled.
+    /// 
+    /// For a async version, use [`download_with_client`].
+    pub fn download_with_client_blocking(&self, client: &reqwest::blocking::Client) -> Result<Vec<u8>, Error> {
+        let response = self.request_blocking(client).send()?;
+        let status = response.status();
+        let crx = response.bytes()?.to_vec();
+        if self.response != "redirect" {
+            return Ok(crx);
+        }
+        let crx = if is_crx_response(status, &crx) {
+            crx
+        } else {
+            self.download_unserved_blocking(client)?
+        };
+        self.check_id(crx)
+    }
+
+    /// Ensures a downloaded CRX is of `x`, if `verify_id` is set.
+    pub(crate) fn check_id(&self, crx: Vec<u8>) -> Result<Vec<u8>, Error> {
+        if self.verify_id {
+            verify_extension_id(&crx, self.x)?;
+        }
+        Ok(crx)
+    }
+
+    /// Downloads the extension as a stream of chunks, so it never has to be held in memory.
+    /// 
+    /// For a blocking version, use [`download_stream_blocking`].
+    pub async fn download_stream(&self) -> Result<impl futures_util::Stream<Item = Result<bytes::Bytes, Error>>, Error> {
         Ok(
-            reqwest::Client::new()
-                .get("https://clients2.google.com/service/update2/crx")
-                .query(&self.to_vec())
+            self.request(shared_client())
                 .send()
                 .await?
-                .bytes()
-                .await?
-                .to_vec()
+                .bytes_stream()
+                .map_err(Error::from)
         )
     }
 
-    /// Downloads the extension.
+    /// Downloads the extension as a reader, so it never has to be held in memory.
+    /// Pairs well with [`CrxToZipWriter`] and [`std::io::copy`].
     /// 
-    /// For a async version, use [`download`].
-    pub fn download_blocking(&self) -> Result<Vec<u8>, reqwest::Error> {
-        Ok(
-            reqwest::blocking::Client::new()
-                .get("https://clients2.google.com/service/update2/crx")
-                .query(&self.to_vec())
-                .send()?
-                .bytes()?
-                .to_vec()
-        )
+    /// For a async version, use [`download_stream`].
+    pub fn download_stream_blocking(&self) -> Result<impl Read, Error> {
+        Ok(self.request_blocking(shared_blocking_client()).send()?)
+    }
+
+    /// Finds the url the CRX is served from, e.g. a versioned `...crx3` url, without downloading it.
+    /// The query should use the `redirect` response, and its first redirect is returned.
+    /// 
+    /// For a blocking version, use [`resolve_url_blocking`].
+    pub async fn resolve_url(&self) -> Result<String, Error> {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            ?;
+        let response = self.request(&client).send().await?;
+        redirect_location(response.url(), response.status(), response.headers())
+    }
+
+    /// Finds the url the CRX is served from, e.g. a versioned `...crx3` url, without downloading it.
+    /// The query should use the `redirect` response, and its first redirect is returned.
+    /// 
+    /// For a async version, use [`resolve_url`].
+    pub fn resolve_url_blocking(&self) -> Result<String, Error> {
+        let client = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            ?;
+        let response = self.request_blocking(&client).send()?;
+        redirect_location(response.url(), response.status(), response.headers())
+    }
+
+    /// Downloads the extension, wrapped for common follow-up actions.
+    /// 
+    /// For a blocking version, use [`download_extension_blocking`].
+    pub async fn download_extension(&self) -> Result<DownloadedExtension, Error> {
+        Ok(DownloadedExtension::new(self.x, self.download().await?))
+    }
+
+    /// Downloads the extension, wrapped for common follow-up actions.
+    /// 
+    /// For a async version, use [`download_extension`].
+    pub fn download_extension_blocking(&self) -> Result<DownloadedExtension, Error> {
+        Ok(DownloadedExtension::new(self.x, self.download_blocking()?))
+    }
+
+    /// Downloads the extension to `path`.
+    /// 
+    /// The file is written atomically, so a failed or interrupted download never leaves a truncated file behind.
+    /// For a blocking version, use [`download_to_file_blocking`].
+    pub async fn download_to_file(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let crx = self.download().await?;
+        fs::write_atomic(path, &crx)
+    }
+
+    /// Downloads the extension to `path`.
+    /// 
+    /// The file is written atomically, so a failed or interrupted download never leaves a truncated file behind.
+    /// For a async version, use [`download_to_file`].
+    pub fn download_to_file_blocking(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let crx = self.download_blocking()?;
+        fs::write_atomic(path, &crx)
+    }
+
+    /// Downloads the extension and unpacks it into `dir`, see [`extract::unpack_to_dir`].
+    /// 
+    /// For a blocking version, use [`download_to_dir_blocking`].
+    pub async fn download_to_dir(&self, dir: impl AsRef<Path>) -> Result<extract::ExtractReport, Error> {
+        extract::unpack_to_dir(&self.download().await?, dir)
+    }
+
+    /// Downloads the extension and unpacks it into `dir`, see [`extract::unpack_to_dir`].
+    /// 
+    /// For a async version, use [`download_to_dir`].
+    pub fn download_to_dir_blocking(&self, dir: impl AsRef<Path>) -> Result<extract::ExtractReport, Error> {
+        extract::unpack_to_dir(&self.download_blocking()?, dir)
     }
 }
+
+/// Whether a redirect query was answered with a CRX, rather than nothing or an error.
+fn is_crx_response(status: reqwest::StatusCode, body: &[u8]) -> bool {
+    status.is_success() && status != reqwest::StatusCode::NO_CONTENT && !body.is_empty()
+}
+
+/// The `Location` of a redirect, resolved against the url it came from.
+fn redirect_location(url: &reqwest::Url, status: reqwest::StatusCode, headers: &reqwest::header::HeaderMap) -> Result<String, Error> {
+    let location = headers
+        .get(reqwest::header::LOCATION)
+        .filter(|_| status.is_redirection())
+        .and_then(|x| x.to_str().ok())
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("expected a redirect, got status {}", status)))?;
+    Ok(url.join(location).map_err(|x| Error::new(ErrorKind::InvalidData, x))?.to_string())
+}
 impl Default for ChromeCRXQuery<'_> {
     fn default() -> Self {
         Self { 
+            endpoint: CHROME_UPDATE_URL,
             response: "redirect",
             os: OperatingSystem::Windows,
             arch: Architecture::AMD64,
@@ -118,42 +481,106 @@ impl Default for ChromeCRXQuery<'_> {
             prodchannel: "unknown",
             prodversion: "9999.0.9999.0",
             acceptformat: "crx2,crx3",
-            x: "" 
+            x: "",
+            installsource: None,
+            version: None,
+            uc: true,
+            user_agent: None,
+            synthesize_user_agent: false,
+            cookie: None,
+            access_token: None,
+            verify_id: true,
         }
     }
 }
 
-/// Not complete!
-/// 
 /// Grabs the public key of a CRX from protobuf, returned as base64 encoded.
-/// It's assumed the reader is correctly positioned.
+/// It's assumed the reader is positioned at the start of the header (12), `end_seek` being where the header ends.
 /// 
-/// View <https://github.com/Rob--W/crxviewer/blob/master/src/lib/crx-to-zip.js#L109> for an implementation.
+/// The key is the one the crx id in `signed_header_data` was derived from, see [`header::CrxFileHeader::public_key`].
+/// Based on <https://github.com/Rob--W/crxviewer/blob/master/src/lib/crx-to-zip.js#L109>.
+pub fn public_key_protobuf(reader: &mut (impl Read + Seek), end_seek: u64) -> Result<String, Error> {
+    let length = end_seek
+        .checked_sub(reader.stream_position()?)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "crx header ends before it starts"))?;
+    let mut buf = Vec::new();
+    reader.take(length).read_to_end(&mut buf)?;
+    if (buf.len() as u64) < length {
+        return Err(CrxError::TruncatedHeader.into());
+    }
+
+    let header = header::CrxFileHeader::decode(&buf)?;
+    let public_key = header
+        .public_key()?
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "crx header has no public key"))?;
+    Ok(general_purpose::STANDARD.encode(public_key))
+}
+
+/// The magic number every ZIP local file header starts with.
+pub const ZIP_MAGIC: [u8; 4] = *b"PK\x03\x04";
+/// The magic number of an empty ZIP, which is just the end of central directory record.
+pub const EMPTY_ZIP_MAGIC: [u8; 4] = *b"PK\x05\x06";
+
+/// Whether `data` starts like a ZIP, see [`ZIP_MAGIC`] and [`EMPTY_ZIP_MAGIC`].
+pub(crate) fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(&ZIP_MAGIC) || data.starts_with(&EMPTY_ZIP_MAGIC)
+}
+
+/// What a file is, as told by its first bytes, see [`detect_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Format {
+    Crx2,
+    Crx3,
+    Zip,
+    Unknown,
+}
+
+/// Tells CRX2, CRX3 and ZIP apart from the first 8 bytes of `data`, without parsing any further.
 /// 
-/// Please contribute if you want this fixed!
-pub fn public_key_protobuf(mut reader: BufReader<Cursor<Vec<u8>>>, end_seek: u64) -> Result<String, Error> {
-    todo!()
+/// Useful for branching on arbitrary files before handing them to a converter.
+pub fn detect_format(data: &[u8]) -> Format {
+    if is_zip(data) {
+        return Format::Zip;
+    }
+    match data.get(..8) {
+        Some([b'C', b'r', b'2', b'4', 2, 0, 0, 0]) => Format::Crx2,
+        Some([b'C', b'r', b'2', b'4', 3, 0, 0, 0]) => Format::Crx3,
+        _ => Format::Unknown,
+    }
+}
+
+/// Whether `data` starts like a CRX of a known version, see [`detect_format`].
+pub fn is_crx(data: &[u8]) -> bool {
+    matches!(detect_format(data), Format::Crx2 | Format::Crx3)
+}
+
+/// Fails unless what was found after a CRX header is a ZIP, which catches truncated or corrupted files early.
+pub(crate) fn check_zip(zip: &[u8]) -> Result<(), Error> {
+    if !is_zip(zip) {
+        return Err(CrxError::NotAZip.into());
+    }
+    Ok(())
 }
 
 /// Converts CRX to ZIP.
 /// 
-/// Set `previous_public_key` to `None. It's used for checking when doing nested CRX files.
+/// Set `previous_public_key` to `None`. It's the key of the outer CRX when unwrapping a nested one,
+/// which is signed by someone else, see [`ConvertedCrx::nested_public_key_b64`] to compare them.
+/// CRX3 files wrapping another CRX, as served by addons.opera.com, are unwrapped to the innermost ZIP.
+/// Input that is already a ZIP (e.g. from addons.mozilla.org, or converted before) is returned unchanged.
 /// 
 /// Credits <https://github.com/Rob--W/crxviewer/blob/master/src/lib/crx-to-zip.js#L16>
 pub fn crx_to_zip(crx: Vec<u8>, previous_public_key: Option<String>) -> Result<Vec<u8>, Error> {
-    let mut reader = BufReader::new(Cursor::new(crx));
-
-    // Ensure is a CRX file
-    let mut magic_number = [0; 4];
-    reader.read_exact(&mut magic_number)?;
-    if String::from_utf8_lossy(&magic_number) != "Cr24" {
-        return Err(Error::new(ErrorKind::InvalidData, "input is not a crx file"));
+    // Already converted
+    if is_zip(&crx) {
+        return Ok(crx);
     }
 
-    // Read the version
-    let mut version = [0; 4];
-    reader.read_exact(&mut version)?;
-    let version = u32::from_le_bytes(version);
+    // Ensure is a CRX file, whose header fits within it
+    let header::CrxHeader { version, archive_offset: zip_start_offset } = header::parse_header(&crx)?;
+    let mut reader = BufReader::new(Cursor::new(crx));
+    reader.seek(SeekFrom::Start(8))?;
 
     // The next four bytes can either be one of the following depending on `version`
     // public_key_length -> version 2
@@ -163,57 +590,280 @@ pub fn crx_to_zip(crx: Vec<u8>, previous_public_key: Option<String>) -> Result<V
     let next_four = u32::from_le_bytes(next_four_buf);
 
     // Special things for each version
-    let (zip_start_offset, public_key_b64) = match version {
-        2 => {        
-            // Read the signature length
-            let mut signature_key_length = [0u8; 4];
-            reader.read_exact(&mut signature_key_length)?;
-            let signature_key_length = u32::from_le_bytes(signature_key_length);
-
-            // Calculate the zip start offset
-            let zip_start_offset = 16 + next_four + signature_key_length;
+    let public_key_b64 = match version {
+        CrxVersion::V2 => {
+            // Skip the signature length, both lengths were checked by `parse_header`
+            reader.seek(SeekFrom::Current(4))?;
 
             // Figure out the public key (we should be at 16 at this stage)
-            let mut pk_buf = [0u8; 4];
+            let mut pk_buf = vec![0u8; next_four as usize];
             reader.read_exact(&mut pk_buf)?;
-            let public_key_b64 = general_purpose::STANDARD.encode(pk_buf);
-
-            // Done
-            (zip_start_offset, public_key_b64)
+            general_purpose::STANDARD.encode(pk_buf)
         },
-        3 => {
-            // Calculate the zip start offset
-            let zip_start_offset = 12 + next_four;
-
+        CrxVersion::V3 => {
             // Figure out the public key (we should be at 12 at this stage)
-            // Does not work, empty string as placeholder
-            let public_key_b64 = String::from("");//public_key_protobuf(reader, zip_start_offset.into())?;
-
-            // Done
-            (zip_start_offset, public_key_b64)
+            public_key_protobuf(&mut reader, zip_start_offset as u64)?
         },
-        _ => return Err(Error::new(ErrorKind::InvalidData, "invalid crx version"))
     };
 
-    // Additional checks for addons.opera.com
-    // They create CRX3 files by prepending the CRX3 header to the CRX2 data.
-    let mut opera_buf = [0; 4];
-    reader.read_exact(&mut opera_buf)?;
-    if version == 3 && String::from_utf8_lossy(&opera_buf) == "Cr24" {
-        // Checking if we got a public key mismatch
-        if previous_public_key.is_some() && previous_public_key.unwrap() != public_key_b64 {
-            println!("Nested CRX: pubkey mismatch; found {}", public_key_b64);
-        }
+    // Strip the header, reusing the allocation of the input
+    let mut out = reader.into_inner().into_inner();
+    out.drain(..zip_start_offset);
 
-        // Repeat the process
-        let mut out: Vec<u8> = Vec::new();
-        reader.read_to_end(&mut out)?;
-        crx_to_zip(out, Some(public_key_b64))?;
+    // Additional checks for addons.opera.com
+    // They create CRX3 files by prepending the CRX3 header to the CRX2 data, so the inner CRX is converted too.
+    // Only one level is unwrapped, so crafted files can't nest headers without end.
+    if version == CrxVersion::V3 && previous_public_key.is_none() && out.starts_with(b"Cr24") {
+        return crx_to_zip(out, Some(public_key_b64));
     }
 
     // Done
-    reader.seek(SeekFrom::Start(zip_start_offset.into()))?;
-    let mut out: Vec<u8> = Vec::new();
-    reader.read_to_end(&mut out)?;
+    check_zip(&out)?;
     Ok(out)
-}
\ No newline at end of file
+}
+
+/// The offset the ZIP starts at within `crx`, 0 if it is already a ZIP.
+fn zip_offset(crx: &[u8]) -> Result<usize, Error> {
+    if is_zip(crx) {
+        return Ok(0);
+    }
+    let header::CrxHeader { version, archive_offset: offset } = header::parse_header(crx)?;
+
+    // addons.opera.com wraps a CRX2 in a CRX3, see [`crx_to_zip`], only one level is unwrapped
+    if version == CrxVersion::V3 && crx[offset..].starts_with(b"Cr24") {
+        let inner = header::parse_header(&crx[offset..])?.archive_offset;
+        check_zip(&crx[offset + inner..])?;
+        return Ok(offset + inner);
+    }
+    check_zip(&crx[offset..])?;
+    Ok(offset)
+}
+
+/// Converts CRX to ZIP without copying, returning the part of `crx` that is the ZIP.
+/// 
+/// Input that is already a ZIP is returned unchanged.
+pub fn crx_to_zip_slice(crx: &[u8]) -> Result<&[u8], Error> {
+    Ok(&crx[zip_offset(crx)?..])
+}
+
+/// Converts CRX to ZIP in place, removing the header from the front of `crx`.
+/// 
+/// The ZIP is moved within the existing allocation, so no second buffer the size of the CRX is needed.
+/// Input that is already a ZIP is left unchanged.
+pub fn crx_to_zip_in_place(crx: &mut Vec<u8>) -> Result<(), Error> {
+    let offset = zip_offset(crx)?;
+    crx.drain(..offset);
+    Ok(())
+}
+
+/// Converts CRX to ZIP without copying, like [`crx_to_zip_slice`] but sharing the buffer of `crx`.
+pub fn crx_to_zip_bytes(crx: bytes::Bytes) -> Result<bytes::Bytes, Error> {
+    Ok(crx.slice(zip_offset(&crx)?..))
+}
+
+/// A CRX converted to ZIP, along with what its header said.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertedCrx {
+    pub zip: Vec<u8>,
+    pub crx_version: CrxVersion,
+    /// The public key the extension id is derived from, base64 encoded.
+    pub public_key_b64: String,
+    pub extension_id: String,
+    /// The length of everything before the archive, the magic number included.
+    pub header_len: usize,
+    /// The public key of the CRX wrapped inside, for files from addons.opera.com, base64 encoded.
+    /// 
+    /// It differs from `public_key_b64` whenever the wrapped CRX was signed by someone else.
+    pub nested_public_key_b64: Option<String>,
+}
+
+/// Converts CRX to ZIP like [`crx_to_zip`], keeping the header information instead of discarding it.
+/// 
+/// Unlike [`crx_to_zip`], the input has to be a CRX. Signatures are not checked, see [`verify_crx`] for that.
+pub fn convert_crx(crx: Vec<u8>) -> Result<ConvertedCrx, Error> {
+    let bundle = signature::extract_signature(&crx)?;
+    let (public_key, extension_id, nested_public_key) = match bundle.version {
+        CrxVersion::V3 => {
+            let header = header::Crx3Header::parse(&crx)?;
+            let public_key = header
+                .file_header
+                .public_key()?
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "crx header has no public key"))?
+                .to_vec();
+            let extension_id = header.extension_id().unwrap_or_else(|| extension_id_from_public_key(&public_key));
+            let nested = &crx[header.archive_offset..];
+            let nested_public_key = if nested.starts_with(b"Cr24") {
+                signature::extract_signature(nested)?.proofs.into_iter().next().map(|x| x.public_key)
+            } else {
+                None
+            };
+            (public_key, extension_id, nested_public_key)
+        },
+        CrxVersion::V2 => {
+            let public_key = bundle.proofs.first().map(|x| x.public_key.clone()).unwrap_or_default();
+            let extension_id = extension_id_from_public_key(&public_key);
+            (public_key, extension_id, None)
+        },
+    };
+
+    Ok(ConvertedCrx {
+        crx_version: bundle.version,
+        public_key_b64: general_purpose::STANDARD.encode(&public_key),
+        extension_id,
+        header_len: bundle.header.len(),
+        nested_public_key_b64: nested_public_key.map(|x| general_purpose::STANDARD.encode(x)),
+        zip: crx_to_zip(crx, None)?,
+    })
+}
+
+/// Converts CRX to ZIP, then validates every entry of the ZIP, see [`archive::validate_zip`].
+/// 
+/// Slower than [`crx_to_zip`], but guarantees the output is a sound archive.
+pub fn crx_to_zip_strict(crx: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let zip = crx_to_zip(crx, None)?;
+    archive::validate_zip(&zip)?;
+    Ok(zip)
+}
+
+/// Finds the offset of the `Cr24` magic number within the first `scan_limit` bytes.
+/// 
+/// Only matches that are followed by a known CRX version are considered.
+pub fn find_crx_start(data: &[u8], scan_limit: usize) -> Option<usize> {
+    let end = data.len().min(scan_limit.saturating_add(8));
+    data[..end]
+        .windows(8)
+        .position(|x| &x[..4] == b"Cr24" && matches!(u32::from_le_bytes(x[4..].try_into().unwrap()), 2 | 3))
+}
+
+/// Converts CRX to ZIP, tolerating junk prepended to the CRX (HTML comment stubs, installer stubs, ...).
+/// 
+/// The first `scan_limit` bytes are searched for the start of the CRX, see [`find_crx_start`].
+/// Returns the offset the CRX was found at, along with the ZIP.
+pub fn crx_to_zip_scan(mut crx: Vec<u8>, scan_limit: usize) -> Result<(usize, Vec<u8>), Error> {
+    let offset = find_crx_start(&crx, scan_limit)
+        .ok_or_else(|| Error::from(CrxError::NotACrx))?;
+    crx.drain(..offset);
+    Ok((offset, crx_to_zip(crx, None)?))
+}
+
+/// Lowercase hex encoding.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+/// The hex encoded SHA-256 of `data`.
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    hex(&Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use crate::mock::crx3;
+    use super::*;
+
+    /// A ZIP with `manifest` as its only file.
+    pub(crate) fn zip_with_manifest(manifest: &str) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("manifest.json", zip::write::SimpleFileOptions::default()).unwrap();
+        zip.write_all(manifest.as_bytes()).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// A ZIP of a minimal extension.
+    fn zip() -> Vec<u8> {
+        zip_with_manifest(r#"{"name":"test","version":"1.0","manifest_version":3}"#)
+    }
+
+    /// A CRX2 around `payload`, with a made up key and signature.
+    pub(crate) fn crx2(payload: &[u8]) -> Vec<u8> {
+        let (public_key, signature) = ([2u8; 16], [3u8; 8]);
+        let mut crx = b"Cr24".to_vec();
+        for x in [2, public_key.len() as u32, signature.len() as u32] {
+            crx.extend(x.to_le_bytes());
+        }
+        crx.extend(public_key);
+        crx.extend(signature);
+        crx.extend(payload);
+        crx
+    }
+
+    /// A CRX3 wrapping a CRX2, the way addons.opera.com serves them.
+    fn nested() -> Vec<u8> {
+        crx3(&crx2(&zip()))
+    }
+
+    #[test]
+    fn extension_id_matches_chromium() {
+        // From components/crx_file/id_util_unittest.cc, GenerateId is what Chrome runs on the public key
+        let cases = [
+            (&b"test"[..], "jpignaibiiemhngfjkcpokkamffknabf"),
+            (b"_", "ncocknphbhhlhkikpnnlmbcnbgdempcd"),
+            (b"this_string_is_longer_than_a_single_sha256_hash_digest", "jimneklojkjdibfkgiiophfhjhbdgcfi"),
+        ];
+        for (key, id) in cases {
+            assert_eq!(extension_id_from_public_key(key), id);
+        }
+    }
+
+    #[test]
+    fn x_param_encodes_values() {
+        let x = XParam { id: "abc", installsource: Some("on demand"), version: Some("1.0&uc=1"), uc: true };
+        assert_eq!(x.to_string(), "id=abc&v=1.0%26uc%3D1&installsource=on%20demand&uc");
+    }
+
+    #[test]
+    fn crx_to_zip_unwraps_nested() {
+        assert_eq!(crx_to_zip(nested(), None).unwrap(), zip());
+        assert_eq!(crx_to_zip(crx3(&zip()), None).unwrap(), zip());
+        assert_eq!(crx_to_zip(crx2(&zip()), None).unwrap(), zip());
+    }
+
+    #[test]
+    fn crx_to_zip_slice_unwraps_nested() {
+        assert_eq!(crx_to_zip_slice(&nested()).unwrap(), zip());
+    }
+
+    #[test]
+    fn crx_to_zip_in_place_unwraps_nested() {
+        let mut crx = nested();
+        crx_to_zip_in_place(&mut crx).unwrap();
+        assert_eq!(crx, zip());
+    }
+
+    #[test]
+    fn crx_to_zip_bytes_unwraps_nested() {
+        assert_eq!(crx_to_zip_bytes(nested().into()).unwrap(), zip());
+    }
+
+    #[test]
+    fn crx_to_zip_writer_unwraps_nested() {
+        let mut writer = CrxToZipWriter::new(Vec::new());
+        writer.write_all(&nested()).unwrap();
+        assert_eq!(writer.finish().unwrap(), zip());
+
+        let mut writer = CrxToZipWriter::new(Vec::new());
+        for x in nested() {
+            writer.write_all(&[x]).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), zip());
+    }
+
+    #[test]
+    fn only_one_level_is_unwrapped() {
+        let crx = crx3(&crx3(&crx2(&zip())));
+        assert!(crx_to_zip(crx.clone(), None).is_err());
+        assert!(crx_to_zip_slice(&crx).is_err());
+        assert!(crx_to_zip_stream(crx.as_slice(), Vec::new()).is_err());
+    }
+
+    #[test]
+    fn convert_crx_reports_nested_key() {
+        let converted = convert_crx(nested()).unwrap();
+        assert_eq!(converted.zip, zip());
+        assert_eq!(converted.public_key_b64, general_purpose::STANDARD.encode([4u8; 16]));
+        assert_eq!(converted.nested_public_key_b64, Some(general_purpose::STANDARD.encode([2u8; 16])));
+        assert_eq!(convert_crx(crx3(&zip())).unwrap().nested_public_key_b64, None);
+    }
+}
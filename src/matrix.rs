@@ -0,0 +1,80 @@
+// Dependencies
+use crate::{sha256_hex, Architecture, ChromeCRXQuery, Error, OperatingSystem};
+
+/// An operating system and architecture to download for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Platform {
+    pub os: OperatingSystem,
+    pub arch: Architecture,
+}
+impl Platform {
+    /// Every combination of `oses` and `arches`.
+    pub fn matrix(oses: &[OperatingSystem], arches: &[Architecture]) -> Vec<Self> {
+        oses.iter()
+            .flat_map(|os| arches.iter().map(|arch| Self { os: os.clone(), arch: arch.clone() }))
+            .collect()
+    }
+
+    /// Every supported combination of operating system and architecture.
+    pub fn all() -> Vec<Self> {
+        use strum::IntoEnumIterator;
+        Self::matrix(&OperatingSystem::iter().collect::<Vec<_>>(), &Architecture::iter().collect::<Vec<_>>())
+    }
+}
+
+/// A distinct package, and every platform it was served for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformPackage {
+    /// The hex encoded SHA-256 of the CRX.
+    pub sha256: String,
+    pub crx: Vec<u8>,
+    pub platforms: Vec<Platform>,
+}
+
+/// Groups the downloads by their hash.
+fn dedup(downloads: Vec<(Platform, Vec<u8>)>) -> Vec<PlatformPackage> {
+    let mut packages: Vec<PlatformPackage> = Vec::new();
+    for (platform, crx) in downloads {
+        let sha256 = sha256_hex(&crx);
+        match packages.iter_mut().find(|x| x.sha256 == sha256) {
+            Some(package) => package.platforms.push(platform),
+            None => packages.push(PlatformPackage { sha256, crx, platforms: vec![platform] }),
+        }
+    }
+    packages
+}
+
+impl ChromeCRXQuery<'_> {
+    /// The same query, for another platform.
+    fn for_platform(&self, platform: &Platform) -> Self {
+        Self {
+            os: platform.os.clone(),
+            arch: platform.arch.clone(),
+            os_arch: platform.arch.clone(),
+            nacl_arch: platform.arch.clone(),
+            ..self.clone()
+        }
+    }
+
+    /// Downloads the extension once per platform, grouping identical packages together.
+    /// 
+    /// For a blocking version, use [`download_matrix_blocking`].
+    pub async fn download_matrix(&self, platforms: &[Platform]) -> Result<Vec<PlatformPackage>, Error> {
+        let mut downloads = Vec::with_capacity(platforms.len());
+        for platform in platforms {
+            downloads.push((platform.clone(), self.for_platform(platform).download().await?));
+        }
+        Ok(dedup(downloads))
+    }
+
+    /// Downloads the extension once per platform, grouping identical packages together.
+    /// 
+    /// For a async version, use [`download_matrix`].
+    pub fn download_matrix_blocking(&self, platforms: &[Platform]) -> Result<Vec<PlatformPackage>, Error> {
+        let downloads = platforms
+            .iter()
+            .map(|platform| Ok((platform.clone(), self.for_platform(platform).download_blocking()?)))
+            .collect::<Result<_, Error>>()?;
+        Ok(dedup(downloads))
+    }
+}
@@ -0,0 +1,42 @@
+// Dependencies
+use std::{fs::File, ops::Deref, path::Path};
+use memmap2::Mmap;
+use crate::{crx_to_zip_slice, Error};
+
+/// The ZIP inside a memory mapped CRX, see [`crx_to_zip_mmap`].
+#[derive(Debug)]
+pub struct MappedZip {
+    mmap: Mmap,
+    /// Where the ZIP starts within the map.
+    offset: usize,
+}
+impl Deref for MappedZip {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.mmap[self.offset..]
+    }
+}
+impl AsRef<[u8]> for MappedZip {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Memory maps the CRX or ZIP at `path` and locates the ZIP inside it, without reading the file into memory.
+/// 
+/// Pages are only loaded as the ZIP is read, e.g. when writing it out with [`crate::fs::write_atomic`].
+/// If the file may change while mapped, use [`crate::crx_to_zip_file`] instead.
+/// 
+/// # Safety
+/// 
+/// The file must not be truncated or modified, by this or any other process, until the [`MappedZip`] is dropped.
+/// Reading past the end of a truncated file raises `SIGBUS` on Unix and kills the process,
+/// and a modified file changes the `&[u8]` under the caller, which is undefined behaviour.
+pub unsafe fn crx_to_zip_mmap(path: impl AsRef<Path>) -> Result<MappedZip, Error> {
+    let file = File::open(path)?;
+    // SAFETY: the caller guarantees the file stays as it is while mapped
+    let mmap = unsafe { Mmap::map(&file)? };
+    let offset = mmap.len() - crx_to_zip_slice(&mmap)?.len();
+    Ok(MappedZip { mmap, offset })
+}
@@ -0,0 +1,281 @@
+// Dependencies
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex},
+    thread::JoinHandle,
+};
+use crate::Error;
+
+// Modules
+mod vcr;
+pub use vcr::VcrServer;
+
+/// How the mock server answers for an extension id.
+#[derive(Debug, Clone)]
+pub enum MockResponse {
+    /// Redirect mode redirects to the CRX, updatecheck mode reports it.
+    Crx { version: String, crx: Vec<u8> },
+    /// Redirect mode returns 204, updatecheck mode still reports the CRX, as the store does for some extensions.
+    /// `hash_sha256` replaces the reported hash, e.g. to test mismatches.
+    Unredirected { version: String, crx: Vec<u8>, hash_sha256: Option<String> },
+    /// Redirect mode returns 204, updatecheck mode reports `noupdate`.
+    NoContent,
+    /// Any other status code, e.g. 500 or 404.
+    Status(u16),
+}
+
+/// A CRX3 around `payload`, whose header has a single RSA proof with a made up key and signature.
+/// 
+/// Its id is that of the made up key, see [`crate::crx_extension_id`], but its signature doesn't verify.
+pub fn crx3(payload: &[u8]) -> Vec<u8> {
+    let (public_key, signature) = ([4u8; 16], [5u8; 8]);
+    let mut proof = vec![0x0a, public_key.len() as u8];
+    proof.extend(public_key);
+    proof.extend([0x12, signature.len() as u8]);
+    proof.extend(signature);
+    let mut header = vec![0x12, proof.len() as u8];
+    header.extend(proof);
+
+    let mut crx = b"Cr24".to_vec();
+    crx.extend(3u32.to_le_bytes());
+    crx.extend((header.len() as u32).to_le_bytes());
+    crx.extend(header);
+    crx.extend(payload);
+    crx
+}
+
+/// State shared with the server thread.
+#[derive(Default)]
+struct State {
+    responses: HashMap<String, MockResponse>,
+    requests: Vec<String>,
+}
+
+/// An in-process stand-in for the Omaha update server, so tests never hit Google.
+///
+/// Point [`crate::ChromeCRXQuery::endpoint`] at [`MockUpdateServer::url`].
+/// Ids without a registered response are answered with [`MockResponse::NoContent`].
+/// 
+/// Opera downloads are answered too, for [`crate::OperaCRXQuery::endpoint`] set to [`MockUpdateServer::root`] and the slug served as an id.
+pub struct MockUpdateServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+impl MockUpdateServer {
+    /// Starts the server on a random local port.
+    pub fn start() -> Result<Self, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = state.clone();
+            spawn_server(listener, shutdown.clone(), move |stream| {
+                let _ = handle(stream, addr, &state);
+            })
+        };
+
+        Ok(Self { addr, state, shutdown, thread: Some(thread) })
+    }
+
+    /// The root of the server, as the Opera add-ons store.
+    pub fn root(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// The update endpoint of the server.
+    pub fn url(&self) -> String {
+        format!("http://{}/service/update2/crx", self.addr)
+    }
+
+    /// Sets how requests for `id` are answered.
+    pub fn serve(&self, id: &str, response: MockResponse) {
+        self.state.lock().unwrap().responses.insert(id.to_string(), response);
+    }
+
+    /// Every request received so far, as `METHOD path?query`.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+impl Drop for MockUpdateServer {
+    fn drop(&mut self) {
+        stop_server(self.addr, &self.shutdown, self.thread.take());
+    }
+}
+
+/// Stops a server started with [`spawn_server`].
+fn stop_server(addr: SocketAddr, shutdown: &AtomicBool, thread: Option<JoinHandle<()>>) {
+    // Wake the accept loop up so it notices
+    shutdown.store(true, Ordering::SeqCst);
+    let _ = TcpStream::connect(addr);
+    if let Some(thread) = thread {
+        let _ = thread.join();
+    }
+}
+
+/// Builds the updatecheck XML for the requested ids.
+fn updatecheck_xml(ids: &[String], addr: SocketAddr, responses: &HashMap<String, MockResponse>) -> String {
+    let apps: String = ids
+        .iter()
+        .map(|id| {
+            let offered = match responses.get(id) {
+                Some(MockResponse::Crx { version, crx }) => Some((version, crx, crate::sha256_hex(crx))),
+                Some(MockResponse::Unredirected { version, crx, hash_sha256 }) => {
+                    Some((version, crx, hash_sha256.clone().unwrap_or_else(|| crate::sha256_hex(crx))))
+                },
+                _ => None,
+            };
+            match offered {
+                Some((version, crx, hash)) => format!(
+                    r#"<app appid="{id}" status="ok"><updatecheck codebase="http://{addr}/crx/{id}.crx" hash_sha256="{hash}" protocol="2.0" size="{}" status="ok" version="{version}"/></app>"#,
+                    crx.len()
+                ),
+                None => format!(r#"<app appid="{id}" status="ok"><updatecheck status="noupdate"/></app>"#),
+            }
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><gupdate xmlns="http://www.google.com/update2/response" protocol="2.0" server="prod">{}</gupdate>"#,
+        apps
+    )
+}
+
+/// Writes a complete response and closes the connection.
+fn respond(mut stream: TcpStream, status: u16, headers: &[(&str, &str)], body: &[u8]) -> Result<(), Error> {
+    let mut head = format!("HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+    Ok(stream.flush()?)
+}
+
+/// Sends a CRX, honouring `Range` as long as `If-Range` matches its `ETag`, the quoted SHA-256.
+fn respond_crx(stream: TcpStream, request: &Request, crx: &[u8]) -> Result<(), Error> {
+    let etag = format!("\"{}\"", crate::sha256_hex(crx));
+    let start = request
+        .header("range")
+        .and_then(|x| x.strip_prefix("bytes=")?.strip_suffix('-')?.parse::<usize>().ok())
+        .filter(|_| request.header("if-range").is_none_or(|x| x == etag));
+
+    let content_type = ("Content-Type", "application/x-chrome-extension");
+    match start {
+        Some(start) if start >= crx.len() => respond(stream, 416, &[("Content-Range", &format!("bytes */{}", crx.len()))], &[]),
+        Some(start) => {
+            let range = format!("bytes {}-{}/{}", start, crx.len() - 1, crx.len());
+            respond(stream, 206, &[content_type, ("ETag", &etag), ("Content-Range", &range)], &crx[start..])
+        },
+        None => respond(stream, 200, &[content_type, ("ETag", &etag)], crx),
+    }
+}
+
+/// The head of a request, as [`read_request`] reads it.
+struct Request {
+    method: String,
+    target: String,
+    /// The names are in lowercase.
+    headers: Vec<(String, String)>,
+}
+impl Request {
+    /// The value of the first header called `name`, which must be lowercase.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(x, _)| x == name).map(|(_, x)| x.as_str())
+    }
+}
+
+/// Reads the head of a request.
+fn read_request(stream: &TcpStream) -> Result<Request, Error> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut headers = Vec::new();
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+    Ok(Request { method, target, headers })
+}
+
+/// Accepts connections until `shutdown` is set, passing each one to `handler`.
+fn spawn_server(listener: TcpListener, shutdown: Arc<AtomicBool>, handler: impl Fn(TcpStream) + Send + 'static) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            if shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            if let Ok(stream) = stream {
+                handler(stream);
+            }
+        }
+    })
+}
+
+/// Answers a single request.
+fn handle(stream: TcpStream, addr: SocketAddr, state: &Mutex<State>) -> Result<(), Error> {
+    let request = read_request(&stream)?;
+    let url = reqwest::Url::parse(&format!("http://{}{}", addr, request.target)).map_err(Error::other)?;
+    let mut state = state.lock().unwrap();
+    state.requests.push(format!("{} {}", request.method, request.target));
+
+    // CRX downloads
+    if let Some(id) = url.path().strip_prefix("/crx/").and_then(|x| x.strip_suffix(".crx")) {
+        return match state.responses.get(id) {
+            Some(MockResponse::Crx { crx, .. } | MockResponse::Unredirected { crx, .. }) => respond_crx(stream, &request, crx),
+            _ => respond(stream, 404, &[], &[]),
+        };
+    }
+    // Opera downloads, by slug
+    if let Some(slug) = url.path().strip_prefix("/extensions/download/").and_then(|x| x.strip_suffix('/')) {
+        return match state.responses.get(slug) {
+            Some(MockResponse::Crx { crx, .. } | MockResponse::Unredirected { crx, .. }) => respond_crx(stream, &request, crx),
+            Some(MockResponse::Status(status)) => respond(stream, *status, &[], &[]),
+            Some(MockResponse::NoContent) => respond(stream, 204, &[], &[]),
+            None => respond(stream, 404, &[], &[]),
+        };
+    }
+    if url.path() != "/service/update2/crx" {
+        return respond(stream, 404, &[], &[]);
+    }
+
+    // Update queries, the id is within the `x` parameter
+    let ids: Vec<String> = url
+        .query_pairs()
+        .filter(|(name, _)| name == "x")
+        .filter_map(|(_, x)| {
+            reqwest::Url::parse(&format!("http://x/?{}", x))
+                .ok()?
+                .query_pairs()
+                .find(|(name, _)| name == "id")
+                .map(|(_, id)| id.to_string())
+        })
+        .collect();
+    let updatecheck = url.query_pairs().any(|(name, value)| name == "response" && value == "updatecheck");
+
+    if updatecheck {
+        let xml = updatecheck_xml(&ids, addr, &state.responses);
+        return respond(stream, 200, &[("Content-Type", "text/xml; charset=UTF-8")], xml.as_bytes());
+    }
+    match ids.first().and_then(|x| state.responses.get(x)) {
+        Some(MockResponse::Crx { .. }) => {
+            let location = format!("http://{}/crx/{}.crx", addr, ids[0]);
+            respond(stream, 302, &[("Location", &location)], &[])
+        },
+        Some(MockResponse::Status(status)) => respond(stream, *status, &[], &[]),
+        Some(MockResponse::Unredirected { .. } | MockResponse::NoContent) | None => respond(stream, 204, &[], &[]),
+    }
+}
@@ -0,0 +1,231 @@
+// Dependencies
+use std::{
+    fs,
+    io::ErrorKind,
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    thread::JoinHandle,
+};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::{json, Value};
+use crate::Error;
+use super::{read_request, respond, Request, spawn_server, stop_server};
+
+/// Proxied urls look like `/__vcr/<scheme>/<host>/<path>`.
+const PREFIX: &str = "/__vcr/";
+/// Response headers worth keeping, the rest describe the transfer rather than the content.
+const KEPT_HEADERS: [&str; 6] = ["content-type", "location", "etag", "last-modified", "x-goog-hash", "content-range"];
+/// Request headers that change the response, a recording only answers requests with the same ones.
+const MATCHED_HEADERS: [&str; 6] = ["range", "if-range", "if-none-match", "if-modified-since", "authorization", "cookie"];
+/// Matched headers that are credentials, kept as their SHA-256 so cassettes can be shared.
+const SECRET_HEADERS: [&str; 2] = ["authorization", "cookie"];
+/// Request headers that aren't forwarded, as they are about the connection to this server.
+const DROPPED_HEADERS: [&str; 5] = ["host", "connection", "content-length", "transfer-encoding", "accept-encoding"];
+
+/// A recorded request and its response.
+#[derive(Debug, Clone)]
+struct Interaction {
+    method: String,
+    url: String,
+    /// The [`MATCHED_HEADERS`] the request was sent with.
+    request_headers: Vec<(String, String)>,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    /// Whether it has already been replayed.
+    used: bool,
+}
+
+/// State shared with the server thread.
+struct Cassette {
+    /// Where to fetch from, `None` when replaying.
+    client: Option<reqwest::blocking::Client>,
+    interactions: Vec<Interaction>,
+}
+
+/// Records real HTTP traffic into a cassette file, and replays it offline.
+///
+/// Requests are sent to the server instead of the real endpoint, see [`VcrServer::url`].
+/// Redirects are rewritten to go through the server too, so the CRX download itself is captured.
+/// Request headers are forwarded, so conditional, resumed and signed in requests can be recorded.
+/// Cassettes are JSON, with base64 encoded bodies. `Authorization` and `Cookie` are only kept as their SHA-256.
+pub struct VcrServer {
+    addr: SocketAddr,
+    path: PathBuf,
+    cassette: Arc<Mutex<Cassette>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+impl VcrServer {
+    /// Starts a server that forwards requests to the real servers, recording them into `cassette`.
+    ///
+    /// The cassette is written when the server is dropped, or by calling [`VcrServer::save`].
+    pub fn record(cassette: impl Into<PathBuf>) -> Result<Self, Error> {
+        let client = reqwest::blocking::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            ?;
+        Self::start(cassette.into(), Cassette { client: Some(client), interactions: Vec::new() })
+    }
+
+    /// Starts a server that answers from a previously recorded `cassette`, without any network access.
+    ///
+    /// Each recorded interaction is used once, in order, with the last match repeating.
+    /// A match has the same method, url and the headers that change the response, such as `Range`, `If-None-Match` or `Cookie`.
+    /// Requests that were never recorded get a 501.
+    pub fn replay(cassette: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = cassette.into();
+        let interactions = parse_cassette(&fs::read(&path)?)?;
+        Self::start(path, Cassette { client: None, interactions })
+    }
+
+    fn start(path: PathBuf, cassette: Cassette) -> Result<Self, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let cassette = Arc::new(Mutex::new(cassette));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let cassette = cassette.clone();
+            spawn_server(listener, shutdown.clone(), move |stream| {
+                let _ = handle(stream, addr, &cassette);
+            })
+        };
+
+        Ok(Self { addr, path, cassette, shutdown, thread: Some(thread) })
+    }
+
+    /// Rewrites `url` so it goes through the server, e.g. for [`crate::ChromeCRXQuery::endpoint`].
+    pub fn url(&self, url: &str) -> String {
+        proxied(self.addr, url)
+    }
+
+    /// Writes the recorded interactions to the cassette. Does nothing when replaying.
+    pub fn save(&self) -> Result<(), Error> {
+        let cassette = self.cassette.lock().unwrap();
+        if cassette.client.is_none() {
+            return Ok(());
+        }
+
+        let interactions: Vec<Value> = cassette.interactions
+            .iter()
+            .map(|x| json!({
+                "method": x.method,
+                "url": x.url,
+                "request_headers": x.request_headers,
+                "status": x.status,
+                "headers": x.headers,
+                "body": general_purpose::STANDARD.encode(&x.body),
+            }))
+            .collect();
+        Ok(fs::write(&self.path, serde_json::to_vec_pretty(&json!({ "interactions": interactions }))?)?)
+    }
+}
+impl Drop for VcrServer {
+    fn drop(&mut self) {
+        stop_server(self.addr, &self.shutdown, self.thread.take());
+        let _ = self.save();
+    }
+}
+
+/// Rewrites an absolute url to go through the server at `addr`.
+fn proxied(addr: SocketAddr, url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, rest)) => format!("http://{}{}{}/{}", addr, PREFIX, scheme, rest),
+        None => url.to_string(),
+    }
+}
+
+/// Reads the interactions out of a cassette.
+fn parse_cassette(data: &[u8]) -> Result<Vec<Interaction>, Error> {
+    let cassette: Value = serde_json::from_slice(data)?;
+    let invalid = || Error::new(ErrorKind::InvalidData, "malformed cassette");
+    cassette["interactions"]
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|x| Ok(Interaction {
+            method: x["method"].as_str().ok_or_else(invalid)?.to_string(),
+            url: x["url"].as_str().ok_or_else(invalid)?.to_string(),
+            request_headers: x.get("request_headers").map(|x| serde_json::from_value(x.clone())).transpose()?.unwrap_or_default(),
+            status: x["status"].as_u64().ok_or_else(invalid)? as u16,
+            headers: serde_json::from_value(x["headers"].clone())?,
+            body: general_purpose::STANDARD
+                .decode(x["body"].as_str().ok_or_else(invalid)?)
+                .map_err(|_| invalid())?,
+            used: false,
+        }))
+        .collect()
+}
+
+/// The [`MATCHED_HEADERS`] of `request`, which a recording must have been sent with too.
+fn match_key(request: &Request) -> Vec<(String, String)> {
+    MATCHED_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = request.header(name)?;
+            let value = match SECRET_HEADERS.contains(name) {
+                true => crate::sha256_hex(value.as_bytes()),
+                false => value.to_string(),
+            };
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Answers a single request, from upstream or the cassette.
+fn handle(stream: TcpStream, addr: SocketAddr, cassette: &Mutex<Cassette>) -> Result<(), Error> {
+    let request = read_request(&stream)?;
+    let (method, request_headers) = (request.method.as_str(), match_key(&request));
+    let Some((scheme, rest)) = request.target.strip_prefix(PREFIX).and_then(|x| x.split_once('/')) else {
+        return respond(stream, 404, &[], &[]);
+    };
+    let url = format!("{}://{}", scheme, rest);
+
+    let mut cassette = cassette.lock().unwrap();
+    let interaction = match &cassette.client {
+        // Fetch for real and keep it
+        Some(client) => {
+            let method = reqwest::Method::from_bytes(method.as_bytes()).map_err(Error::other)?;
+            let forwarded = request.headers.iter().filter(|(name, _)| !DROPPED_HEADERS.contains(&name.as_str()));
+            let response = forwarded
+                .fold(client.request(method.clone(), &url), |builder, (name, value)| builder.header(name, value))
+                .send()
+                ?;
+            let status = response.status().as_u16();
+            let headers = response.headers()
+                .iter()
+                .filter(|(name, _)| KEPT_HEADERS.contains(&name.as_str()))
+                .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+                .collect();
+            let body = response.bytes()?.to_vec();
+            let interaction = Interaction { method: method.to_string(), url, request_headers, status, headers, body, used: true };
+            cassette.interactions.push(interaction.clone());
+            interaction
+        },
+        // Play it back
+        None => {
+            let matching = |x: &Interaction| x.method == method && x.url == url && x.request_headers == request_headers;
+            let index = cassette.interactions.iter().position(|x| matching(x) && !x.used)
+                .or_else(|| cassette.interactions.iter().rposition(matching));
+            let Some(index) = index else {
+                return respond(stream, 501, &[], b"no recorded interaction");
+            };
+            cassette.interactions[index].used = true;
+            cassette.interactions[index].clone()
+        },
+    };
+    drop(cassette);
+
+    // Keep following redirects through the server
+    let headers: Vec<(String, String)> = interaction.headers
+        .into_iter()
+        .map(|(name, value)| match name.as_str() {
+            "location" => (name, proxied(addr, &value)),
+            _ => (name, value),
+        })
+        .collect();
+    let headers: Vec<(&str, &str)> = headers.iter().map(|x| (x.0.as_str(), x.1.as_str())).collect();
+    respond(stream, interaction.status, &headers, &interaction.body)
+}
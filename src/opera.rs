@@ -0,0 +1,108 @@
+// Dependencies
+use reqwest::StatusCode;
+use crate::{crx_to_zip, Error};
+
+/// The root of the Opera add-ons store.
+pub const OPERA_ADDONS_URL: &str = "https://addons.opera.com";
+
+/// Downloads an extension hosted on addons.opera.com, which can't be reached through the Chrome endpoint.
+#[derive(Clone)]
+pub struct OperaCRXQuery<'a> {
+    /// The store root, [`OPERA_ADDONS_URL`] by default.
+    pub endpoint: &'a str,
+    /// The extension's slug, as in `addons.opera.com/extensions/details/<slug>/`.
+    pub slug: &'a str,
+    /// The `User-Agent` header, the store only serves Opera.
+    pub user_agent: &'a str,
+}
+impl<'a> OperaCRXQuery<'a> {
+    /// Creates a query for `slug`, against the public store.
+    pub fn new(slug: &'a str) -> Self {
+        Self {
+            endpoint: OPERA_ADDONS_URL,
+            slug,
+            user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Safari/537.36 OPR/124.0.0.0",
+        }
+    }
+
+    /// The url the CRX is downloaded from, with the slug percent-encoded so it stays a single path segment.
+    pub fn url(&self) -> String {
+        format!("{}/extensions/download/{}/", self.endpoint.trim_end_matches('/'), crate::url_encode(self.slug))
+    }
+
+    /// Downloads the extension.
+    /// 
+    /// Fails with [`Error::Status`] if the store doesn't answer with a success, and [`Error::EmptyResponse`] if it answers without a body.
+    /// For a blocking version, use [`download_blocking`].
+    pub async fn download(&self) -> Result<Vec<u8>, Error> {
+        let response = crate::shared_client()
+            .get(self.url())
+            .header(reqwest::header::USER_AGENT, self.user_agent)
+            .send()
+            .await?;
+        let status = response.status();
+        check_body(status, response.bytes().await?.to_vec())
+    }
+
+    /// Downloads the extension.
+    /// 
+    /// Fails with [`Error::Status`] if the store doesn't answer with a success, and [`Error::EmptyResponse`] if it answers without a body.
+    /// For a async version, use [`download`].
+    pub fn download_blocking(&self) -> Result<Vec<u8>, Error> {
+        let response = crate::shared_blocking_client()
+            .get(self.url())
+            .header(reqwest::header::USER_AGENT, self.user_agent)
+            .send()?;
+        let status = response.status();
+        check_body(status, response.bytes()?.to_vec())
+    }
+
+    /// Downloads the extension as a ZIP, see [`opera_crx_to_zip`].
+    /// 
+    /// For a blocking version, use [`download_zip_blocking`].
+    pub async fn download_zip(&self) -> Result<Vec<u8>, Error> {
+        opera_crx_to_zip(self.download().await?)
+    }
+
+    /// Downloads the extension as a ZIP, see [`opera_crx_to_zip`].
+    /// 
+    /// For a async version, use [`download_zip`].
+    pub fn download_zip_blocking(&self) -> Result<Vec<u8>, Error> {
+        opera_crx_to_zip(self.download_blocking()?)
+    }
+}
+
+/// Turns a response that isn't a success, or has no body, into an error.
+fn check_body(status: StatusCode, body: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if !status.is_success() {
+        return Err(Error::Status(status));
+    }
+    if status == StatusCode::NO_CONTENT || body.is_empty() {
+        return Err(Error::EmptyResponse(status));
+    }
+    Ok(body)
+}
+
+/// Converts an Opera CRX to ZIP.
+/// 
+/// addons.opera.com wraps a CRX2 inside of a CRX3, so headers are stripped until the ZIP is reached.
+pub fn opera_crx_to_zip(crx: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let mut data = crx_to_zip(crx, None)?;
+    while data.starts_with(b"Cr24") {
+        data = crx_to_zip(data, None)?;
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_keeps_slug_in_one_segment() {
+        let query = OperaCRXQuery { endpoint: "https://addons.opera.com/", ..OperaCRXQuery::new("ublock") };
+        assert_eq!(query.url(), "https://addons.opera.com/extensions/download/ublock/");
+        let query = OperaCRXQuery::new("../../admin/x y");
+        assert_eq!(query.url(), "https://addons.opera.com/extensions/download/..%2F..%2Fadmin%2Fx%20y/");
+    }
+}
@@ -0,0 +1,9 @@
+//! The commonly used types and functions, for glob importing with `use crx_dl::prelude::*`.
+
+pub use crate::{
+    crx_to_zip, crx_to_zip_file,
+    extract::{extract_zip, unpack_to_dir, ExtractOptions},
+    fetch, fetch_blocking, fetch_to, fetch_to_blocking,
+    sink::{DirSink, Sink},
+    Architecture, ChromeCRXQuery, DownloadResponse, DownloadedExtension, Downloader, DownloaderOptions, OperatingSystem, Preset,
+};
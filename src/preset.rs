@@ -0,0 +1,60 @@
+// Dependencies
+use crate::{ChromeCRXQuery, ProductId, EDGE_UPDATE_URL};
+
+/// The Chrome version claimed by the presets.
+pub const PRESET_CHROME_VERSION: &str = "140.0.7339.128";
+
+/// Known-good query parameters for each browser.
+/// 
+/// Use as `ChromeCRXQuery { x: id, ..Preset::Edge.query() }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumString)]
+pub enum Preset {
+    Chrome,
+    /// Downloads from the Edge Add-ons store.
+    Edge,
+    Brave,
+    Vivaldi,
+    /// Installing from the Chrome Web Store through Opera.
+    Opera,
+}
+impl Preset {
+    /// The query parameters, with everything but `x` filled in.
+    pub fn query(&self) -> ChromeCRXQuery<'static> {
+        let defaults = ChromeCRXQuery {
+            prodchannel: "stable",
+            prodversion: PRESET_CHROME_VERSION,
+            ..Default::default()
+        };
+
+        match self {
+            Self::Chrome => ChromeCRXQuery {
+                prod: ProductId::ChromeCRX,
+                user_agent: Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Safari/537.36"),
+                ..defaults
+            },
+            Self::Edge => ChromeCRXQuery {
+                endpoint: EDGE_UPDATE_URL,
+                prod: ProductId::ChromiumCRX,
+                prodchannel: "",
+                user_agent: Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Safari/537.36 Edg/140.0.0.0"),
+                ..defaults
+            },
+            // Brave and Vivaldi identify as Chrome
+            Self::Brave | Self::Vivaldi => ChromeCRXQuery {
+                prod: ProductId::ChromiumCRX,
+                user_agent: Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Safari/537.36"),
+                ..defaults
+            },
+            Self::Opera => ChromeCRXQuery {
+                prod: ProductId::ChromiumCRX,
+                user_agent: Some("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Safari/537.36 OPR/124.0.0.0"),
+                ..defaults
+            },
+        }
+    }
+
+    /// The query for downloading `id`, e.g. `Preset::Edge.for_extension(id).download_blocking()` for Edge-only extensions.
+    pub fn for_extension<'a>(&self, id: &'a str) -> ChromeCRXQuery<'a> {
+        ChromeCRXQuery { x: id, ..self.query() }
+    }
+}
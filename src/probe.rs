@@ -0,0 +1,126 @@
+// Dependencies
+use reqwest::header::{self, HeaderMap};
+use crate::{updatecheck::parse_updatecheck, ChromeCRXQuery, Error};
+
+/// What a `HEAD` request reveals about a CRX, see [`ChromeCRXQuery::head`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrxMetadata {
+    /// The resolved url of the CRX.
+    pub url: String,
+    pub content_length: Option<u64>,
+    pub last_modified: Option<String>,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+}
+impl CrxMetadata {
+    fn new(url: String, headers: &HeaderMap) -> Self {
+        let get = |name| headers
+            .get(name)
+            .and_then(|x| x.to_str().ok())
+            .map(str::to_string);
+        Self {
+            url,
+            content_length: get(header::CONTENT_LENGTH).and_then(|x| x.parse().ok()),
+            last_modified: get(header::LAST_MODIFIED),
+            content_type: get(header::CONTENT_TYPE),
+            etag: get(header::ETAG),
+        }
+    }
+}
+
+/// Whether an updatecheck response offered the requested extension.
+fn offered(xml: &[u8]) -> Result<bool, Error> {
+    let checks = parse_updatecheck(&String::from_utf8_lossy(xml))?;
+    Ok(checks.first().is_some_and(|x| x.is_ok()))
+}
+
+impl ChromeCRXQuery<'_> {
+    /// Whether the store currently serves the extension to this query (checked with `response=updatecheck`).
+    /// 
+    /// For a blocking version, use [`is_served_blocking`].
+    pub async fn is_served(&self) -> Result<bool, Error> {
+        let query = ChromeCRXQuery { response: "updatecheck", ..self.clone() };
+        offered(&query.download().await?)
+    }
+
+    /// Whether the store currently serves the extension to this query (checked with `response=updatecheck`).
+    /// 
+    /// For a async version, use [`is_served`].
+    pub fn is_served_blocking(&self) -> Result<bool, Error> {
+        let query = ChromeCRXQuery { response: "updatecheck", ..self.clone() };
+        offered(&query.download_blocking()?)
+    }
+
+    /// Finds the oldest Chrome major version the store still serves the extension to,
+    /// by bisecting `prodversion` between 1 and `max_major`.
+    /// 
+    /// Assumes that once a version is served, every newer one is too.
+    /// Returns `None` if not even `max_major` is served.
+    /// For a blocking version, use [`probe_min_version_blocking`].
+    pub async fn probe_min_version(&self, max_major: u32) -> Result<Option<u32>, Error> {
+        let (mut low, mut high) = (1, max_major);
+        let mut found = None;
+        while low <= high {
+            let major = low + (high - low) / 2;
+            let version = format!("{}.0.0.0", major);
+            if (ChromeCRXQuery { prodversion: &version, ..self.clone() }).is_served().await? {
+                found = Some(major);
+                high = major - 1;
+            } else {
+                low = major + 1;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Finds the oldest Chrome major version the store still serves the extension to,
+    /// by bisecting `prodversion` between 1 and `max_major`.
+    /// 
+    /// Assumes that once a version is served, every newer one is too.
+    /// Returns `None` if not even `max_major` is served.
+    /// For a async version, use [`probe_min_version`].
+    pub fn probe_min_version_blocking(&self, max_major: u32) -> Result<Option<u32>, Error> {
+        let (mut low, mut high) = (1, max_major);
+        let mut found = None;
+        while low <= high {
+            let major = low + (high - low) / 2;
+            let version = format!("{}.0.0.0", major);
+            if (ChromeCRXQuery { prodversion: &version, ..self.clone() }).is_served_blocking()? {
+                found = Some(major);
+                high = major - 1;
+            } else {
+                low = major + 1;
+            }
+        }
+        Ok(found)
+    }
+
+    /// Looks up the size and age of the CRX with a `HEAD` request to its resolved url, see [`ChromeCRXQuery::resolve_url`].
+    /// Nothing is downloaded, so this is cheap enough to run before deciding to.
+    /// 
+    /// For a blocking version, use [`head_blocking`].
+    pub async fn head(&self) -> Result<CrxMetadata, Error> {
+        let url = self.resolve_url().await?;
+        let response = crate::shared_client()
+            .head(&url)
+            .send()
+            .await
+            .and_then(|x| x.error_for_status())
+            ?;
+        Ok(CrxMetadata::new(url, response.headers()))
+    }
+
+    /// Looks up the size and age of the CRX with a `HEAD` request to its resolved url, see [`ChromeCRXQuery::resolve_url_blocking`].
+    /// Nothing is downloaded, so this is cheap enough to run before deciding to.
+    /// 
+    /// For a async version, use [`head`].
+    pub fn head_blocking(&self) -> Result<CrxMetadata, Error> {
+        let url = self.resolve_url_blocking()?;
+        let response = crate::shared_blocking_client()
+            .head(&url)
+            .send()
+            .and_then(|x| x.error_for_status())
+            ?;
+        Ok(CrxMetadata::new(url, response.headers()))
+    }
+}
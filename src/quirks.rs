@@ -0,0 +1,75 @@
+// Dependencies
+use std::io::ErrorKind;
+use crate::{find_crx_start, is_zip, signature::extract_signature, CrxError, Error};
+
+/// How far from where they should be the CRX magic number and the ZIP are searched for.
+pub const QUIRK_SEARCH_LIMIT: usize = 4096;
+
+/// A deviation from the CRX format, seen in files from third-party packers (node-crx, the Python `crx3` package, ...)
+/// and old Chromium builds, that was tolerated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Quirk {
+    /// Bytes before the magic number, which were skipped.
+    LeadingData(usize),
+    /// The ZIP did not start where the header said it would, due to padding or a misstated header length.
+    ZipOffsetMismatch { declared: usize, actual: usize },
+    /// The header has no public keys or signatures at all.
+    MissingProofs,
+    /// The CRX3 header could not be decoded, but the ZIP could still be found.
+    MalformedHeader(String),
+}
+
+/// Whether a ZIP starts at `offset`.
+fn zip_at(crx: &[u8], offset: usize) -> bool {
+    crx.get(offset..).is_some_and(is_zip)
+}
+
+/// Converts CRX to ZIP like [`crate::crx_to_zip`], but tolerates the quirks of real-world packers instead of failing.
+/// 
+/// Every quirk found is returned alongside the ZIP, so callers can warn about them.
+pub fn crx_to_zip_tolerant(crx: &[u8]) -> Result<(Vec<u8>, Vec<Quirk>), Error> {
+    let mut quirks = Vec::new();
+
+    // Skip anything before the magic number
+    let start = find_crx_start(crx, QUIRK_SEARCH_LIMIT)
+        .ok_or_else(|| Error::from(CrxError::NotACrx))?;
+    if start > 0 {
+        quirks.push(Quirk::LeadingData(start));
+    }
+    let crx = &crx[start..];
+
+    // Where the header says the ZIP starts
+    let read_u32 = |offset: usize| crx
+        .get(offset..offset + 4)
+        .map(|x| u32::from_le_bytes(x.try_into().unwrap()) as usize)
+        .ok_or_else(|| Error::from(CrxError::TruncatedHeader));
+    let (minimum, declared) = match read_u32(4)? {
+        2 => (16, 16usize.saturating_add(read_u32(8)?).saturating_add(read_u32(12)?)),
+        _ => (12, 12usize.saturating_add(read_u32(8)?)),
+    };
+
+    // Check the proofs, without letting a bad header stop the conversion
+    match extract_signature(crx) {
+        Ok(bundle) if bundle.proofs.iter().all(|x| x.public_key.is_empty() || x.signature.is_empty()) => quirks.push(Quirk::MissingProofs),
+        Ok(_) => {},
+        Err(x) => quirks.push(Quirk::MalformedHeader(x.to_string())),
+    }
+
+    // Find the ZIP, nearest to where it should be first
+    let actual = if zip_at(crx, declared) {
+        declared
+    } else {
+        let after = (declared..declared.saturating_add(QUIRK_SEARCH_LIMIT).min(crx.len())).find(|x| zip_at(crx, *x));
+        let before = (minimum.max(declared.saturating_sub(QUIRK_SEARCH_LIMIT))..declared.min(crx.len())).rev().find(|x| zip_at(crx, *x));
+        let actual = after
+            .into_iter()
+            .chain(before)
+            .min_by_key(|x| x.abs_diff(declared))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "could not find the zip inside the crx"))?;
+        quirks.push(Quirk::ZipOffsetMismatch { declared, actual });
+        actual
+    };
+
+    // Done
+    Ok((crx[actual..].to_vec(), quirks))
+}
@@ -0,0 +1,69 @@
+// Dependencies
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How fast a [`crate::Downloader`] may go, shared by everything it downloads.
+/// 
+/// Both limits are token buckets, allowing bursts of up to a second's worth (at least one request).
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    /// The most requests sent per minute, including retries and segments.
+    pub requests_per_minute: Option<u32>,
+    /// The most response body bytes received per second.
+    pub bytes_per_second: Option<u64>,
+}
+
+/// A token bucket, which can go into debt and then has to be waited out.
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens added per second.
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+impl Bucket {
+    fn new(rate: f64) -> Self {
+        let capacity = rate.max(1.0);
+        Self { rate, capacity, tokens: capacity, updated: Instant::now() }
+    }
+
+    /// Takes `amount` tokens, returning how long to wait until the bucket is out of debt.
+    fn take(&mut self, amount: f64) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity) - amount;
+        self.updated = now;
+        if self.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(-self.tokens / self.rate)
+    }
+}
+
+/// Enforces a [`RateLimit`], telling callers how long to wait.
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+    requests: Option<Mutex<Bucket>>,
+    bytes: Option<Mutex<Bucket>>,
+}
+impl RateLimiter {
+    pub(crate) fn new(limit: &RateLimit) -> Self {
+        Self {
+            requests: limit.requests_per_minute.filter(|x| *x > 0).map(|x| Mutex::new(Bucket::new(x as f64 / 60.0))),
+            bytes: limit.bytes_per_second.filter(|x| *x > 0).map(|x| Mutex::new(Bucket::new(x as f64))),
+        }
+    }
+
+    /// How long to wait before sending a request, if at all.
+    pub(crate) fn request(&self) -> Option<Duration> {
+        Some(self.requests.as_ref()?.lock().unwrap().take(1.0)).filter(|x| !x.is_zero())
+    }
+
+    /// How long to wait after receiving `bytes`, before reading more, if at all.
+    pub(crate) fn bytes(&self, bytes: usize) -> Option<Duration> {
+        Some(self.bytes.as_ref()?.lock().unwrap().take(bytes as f64)).filter(|x| !x.is_zero())
+    }
+}
@@ -0,0 +1,52 @@
+// Dependencies
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+use reqwest::StatusCode;
+
+/// When and how often failed requests are retried.
+/// 
+/// Connection errors and `408`, `429` and `5xx` responses are retried, as long as none of the body was written yet.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// How many times a request is sent at most, `1` disables retries.
+    pub max_attempts: u32,
+    /// How long to wait before the first retry, doubling after each one.
+    pub initial_backoff: Duration,
+    /// The longest wait between attempts.
+    pub max_backoff: Duration,
+    /// Randomize each wait to between half and all of it, so many clients don't retry in lockstep.
+    pub jitter: bool,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+impl RetryPolicy {
+    /// How long to wait after the `attempt`th attempt failed, starting from 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff);
+        if !self.jitter {
+            return backoff;
+        }
+
+        // Any randomness will do, so avoid a dependency
+        let random = RandomState::new().build_hasher().finish();
+        backoff / 2 + backoff.mul_f64((random % 1000) as f64 / 2000.0)
+    }
+
+    /// Whether a response with `status` is worth retrying.
+    pub fn is_retryable(status: StatusCode) -> bool {
+        status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+}
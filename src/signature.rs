@@ -0,0 +1,128 @@
+// Dependencies
+use std::io::{Read, Write};
+use crate::{header::{CrxFileHeader, CrxVersion, UnknownField}, CrxError, Error};
+
+/// The algorithm a [`Proof`] was made with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum ProofAlgorithm {
+    /// Used by CRX2 files.
+    #[strum(serialize="sha1_with_rsa")]
+    Sha1WithRsa,
+    #[strum(serialize="sha256_with_rsa")]
+    Sha256WithRsa,
+    #[strum(serialize="sha256_with_ecdsa")]
+    Sha256WithEcdsa,
+}
+
+/// A public key and the signature made with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    pub algorithm: ProofAlgorithm,
+    /// DER encoded `SubjectPublicKeyInfo`.
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// Everything needed to re-verify a CRX, detached from its zip.
+///
+/// The sidecar format is the raw CRX header, so `.sig` + `.zip` concatenated is the original CRX.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureBundle {
+    pub version: CrxVersion,
+    /// The raw header, from the magic number up to the start of the zip.
+    pub header: Vec<u8>,
+    pub proofs: Vec<Proof>,
+    /// The serialized `SignedData` message, CRX3 only.
+    pub signed_header_data: Option<Vec<u8>>,
+    /// Header fields this crate does not know about, CRX3 only.
+    pub unknown_fields: Vec<UnknownField>,
+}
+impl SignatureBundle {
+    /// All of the public keys that signed the CRX.
+    pub fn public_keys(&self) -> Vec<&[u8]> {
+        self.proofs
+            .iter()
+            .map(|x| x.public_key.as_slice())
+            .collect()
+    }
+
+    /// Writes the bundle as a `.sig` sidecar.
+    pub fn write_to(&self, mut writer: impl Write) -> Result<(), Error> {
+        Ok(writer.write_all(&self.header)?)
+    }
+
+    /// Reads a bundle from a `.sig` sidecar.
+    pub fn read_from(mut reader: impl Read) -> Result<Self, Error> {
+        let mut header = Vec::new();
+        reader.read_to_end(&mut header)?;
+        extract_signature(&header)
+    }
+}
+
+/// Reads a little endian `u32` at `offset`.
+fn read_u32(crx: &[u8], offset: usize) -> Result<u32, Error> {
+    crx.get(offset..offset + 4)
+        .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+        .ok_or_else(|| Error::from(CrxError::TruncatedHeader))
+}
+
+/// Takes `len` bytes at `offset`.
+fn read_bytes(crx: &[u8], offset: usize, len: u32) -> Result<&[u8], Error> {
+    offset.checked_add(len as usize)
+        .and_then(|end| crx.get(offset..end))
+        .ok_or_else(|| Error::from(CrxError::TruncatedHeader))
+}
+
+/// Extracts the header, public keys and signatures of a CRX.
+///
+/// Only the header needs to be present, so this also accepts the contents of a `.sig` sidecar.
+pub fn extract_signature(crx: &[u8]) -> Result<SignatureBundle, Error> {
+    // Ensure is a CRX file
+    if crx.get(..4) != Some(b"Cr24") {
+        return Err(CrxError::NotACrx.into());
+    }
+
+    let version = CrxVersion::try_from(read_u32(crx, 4)?)?;
+    match version {
+        CrxVersion::V2 => {
+            // Public key, then the signature over the zip
+            let public_key_length = read_u32(crx, 8)?;
+            let signature_length = read_u32(crx, 12)?;
+            let public_key = read_bytes(crx, 16, public_key_length)?;
+            let signature = read_bytes(crx, 16 + public_key.len(), signature_length)?;
+            let header_end = 16 + public_key.len() + signature.len();
+
+            Ok(SignatureBundle {
+                version,
+                header: crx[..header_end].to_vec(),
+                proofs: vec![Proof {
+                    algorithm: ProofAlgorithm::Sha1WithRsa,
+                    public_key: public_key.to_vec(),
+                    signature: signature.to_vec(),
+                }],
+                signed_header_data: None,
+                unknown_fields: Vec::new(),
+            })
+        },
+        CrxVersion::V3 => {
+            // Protobuf encoded `CrxFileHeader`
+            let header_length = read_u32(crx, 8)?;
+            let header_bytes = read_bytes(crx, 12, header_length)?;
+            let header = CrxFileHeader::decode(header_bytes)?;
+            let header_end = 12 + header_bytes.len();
+
+            let rsa = header.sha256_with_rsa.into_iter().map(|x| (ProofAlgorithm::Sha256WithRsa, x));
+            let ecdsa = header.sha256_with_ecdsa.into_iter().map(|x| (ProofAlgorithm::Sha256WithEcdsa, x));
+            Ok(SignatureBundle {
+                version,
+                header: crx[..header_end].to_vec(),
+                proofs: rsa
+                    .chain(ecdsa)
+                    .map(|(algorithm, x)| Proof { algorithm, public_key: x.public_key, signature: x.signature })
+                    .collect(),
+                signed_header_data: header.signed_header_data,
+                unknown_fields: header.unknown_fields,
+            })
+        },
+    }
+}
@@ -0,0 +1,84 @@
+// Dependencies
+use std::{fs, io::ErrorKind, path::{Component, Path, PathBuf}};
+use crate::{fs::Sha256Sums, Error};
+
+// Modules
+mod archive;
+pub use archive::{TarSink, TAR_MANIFEST_NAME};
+#[cfg(feature = "github")]
+mod github;
+#[cfg(feature = "github")]
+pub use github::GitHubReleaseSink;
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(feature = "s3")]
+pub use s3::S3Sink;
+
+/// A destination for downloaded artifacts (`.crx`, `.zip`, ...).
+///
+/// Keys are `/` separated relative paths, such as `<id>.crx` or `<id>/<version>.zip`, that never leave their root.
+pub trait Sink {
+    /// Stores `data` under `key`, replacing anything already stored there.
+    fn put(&mut self, key: &str, data: &[u8]) -> Result<(), Error>;
+}
+
+/// Writes artifacts into a directory on the local filesystem.
+pub struct DirSink {
+    /// The directory keys are resolved against.
+    pub dir: PathBuf,
+    /// Write a `.sha256` sidecar next to each artifact.
+    pub sha256_sidecars: bool,
+    /// Collects checksums for [`DirSink::write_sha256sums`].
+    pub sha256sums: Sha256Sums,
+}
+impl DirSink {
+    /// Creates a sink writing into `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), sha256_sidecars: false, sha256sums: Sha256Sums::default() }
+    }
+
+    /// Writes a combined `SHA256SUMS` file covering everything stored so far.
+    pub fn write_sha256sums(&self) -> Result<(), Error> {
+        self.sha256sums.write(&self.dir)
+    }
+}
+impl Sink for DirSink {
+    /// Fails with [`ErrorKind::InvalidInput`] if `key` is absolute or has `.` or `..` components.
+    fn put(&mut self, key: &str, data: &[u8]) -> Result<(), Error> {
+        let relative = Path::new(key);
+        if key.is_empty() || !relative.components().all(|x| matches!(x, Component::Normal(_))) {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("sink key {:?} is not a relative path inside the sink", key)));
+        }
+        let path = self.dir.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        crate::fs::write_atomic(&path, data)?;
+        if self.sha256_sidecars {
+            crate::fs::write_sha256_sidecar(&path, data)?;
+        }
+        self.sha256sums.add(key, data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dir_sink_keeps_keys_inside() {
+        let dir = std::env::temp_dir().join(format!("crx-dl-sink-{}", std::process::id()));
+        let mut sink = DirSink::new(dir.join("out"));
+
+        sink.put("a/b.crx", b"crx").unwrap();
+        assert_eq!(fs::read(dir.join("out/a/b.crx")).unwrap(), b"crx");
+        for key in ["", "../escaped.crx", "a/../../escaped.crx", "./a.crx", "/tmp/escaped.crx"] {
+            let error = sink.put(key, b"crx").unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput, "{:?}", key);
+        }
+        assert!(!dir.join("escaped.crx").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+// Dependencies
+use std::{io::Write, time::{SystemTime, UNIX_EPOCH}};
+use crate::Error;
+use super::Sink;
+
+/// The name of the manifest entry written by [`TarSink::finish`].
+pub const TAR_MANIFEST_NAME: &str = "MANIFEST.tsv";
+
+/// Appends artifacts into a single tar archive instead of many small files.
+///
+/// Once done, call [`TarSink::finish`] to write the manifest, which has one `key\tsize\tsha256` line per artifact.
+pub struct TarSink<W: Write> {
+    builder: tar::Builder<W>,
+    manifest: Vec<(String, usize, String)>,
+}
+impl<W: Write> TarSink<W> {
+    /// Creates a sink writing the archive into `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            builder: tar::Builder::new(writer),
+            manifest: Vec::new(),
+        }
+    }
+
+    /// Appends a single entry to the archive.
+    fn append(&mut self, key: &str, data: &[u8]) -> Result<(), Error> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs());
+        Ok(self.builder.append_data(&mut header, key, data)?)
+    }
+
+    /// Writes the manifest and the end of archive marker, returning the writer.
+    pub fn finish(mut self) -> Result<W, Error> {
+        let manifest: String = self.manifest
+            .iter()
+            .map(|x| format!("{}\t{}\t{}\n", x.0, x.1, x.2))
+            .collect();
+        self.append(TAR_MANIFEST_NAME, manifest.as_bytes())?;
+        Ok(self.builder.into_inner()?)
+    }
+}
+impl<W: Write> Sink for TarSink<W> {
+    fn put(&mut self, key: &str, data: &[u8]) -> Result<(), Error> {
+        self.append(key, data)?;
+        self.manifest.push((key.to_string(), data.len(), crate::sha256_hex(data)));
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+// Dependencies
+use std::io::ErrorKind;
+use reqwest::{blocking::{RequestBuilder, Response}, StatusCode};
+use serde_json::{json, Value};
+use crate::{fs::{Sha256Sums, SHA256SUMS_NAME}, Error};
+use super::Sink;
+
+/// Uploads artifacts as assets of a GitHub Release, creating the release if needed.
+///
+/// Asset names can't contain `/`, so keys are flattened with `.` (`<id>/<version>.zip` becomes `<id>.<version>.zip`).
+/// Assets that already exist are replaced.
+pub struct GitHubReleaseSink {
+    /// The REST API root, change for GitHub Enterprise.
+    pub api_url: String,
+    pub owner: String,
+    pub repo: String,
+    /// The tag of the release, which is created on the default branch if missing.
+    pub tag: String,
+    /// A token allowed to write to the repository's contents.
+    pub token: String,
+    /// Upload a `.sha256` asset alongside each artifact.
+    pub sha256_sidecars: bool,
+    /// Collects checksums for [`GitHubReleaseSink::upload_sha256sums`].
+    pub sha256sums: Sha256Sums,
+    /// The release, once looked up.
+    release: Option<Value>,
+    client: reqwest::blocking::Client,
+}
+impl GitHubReleaseSink {
+    /// Creates a sink uploading to the release tagged `tag` in `owner/repo`.
+    pub fn new(owner: &str, repo: &str, tag: &str, token: &str) -> Self {
+        Self {
+            api_url: String::from("https://api.github.com"),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            tag: tag.to_string(),
+            token: token.to_string(),
+            sha256_sidecars: false,
+            sha256sums: Sha256Sums::default(),
+            release: None,
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+
+    /// The asset name `key` is uploaded as.
+    pub fn asset_name(key: &str) -> String {
+        key.replace('/', ".")
+    }
+
+    /// Adds the headers every API request needs.
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+            .bearer_auth(&self.token)
+            .header("accept", "application/vnd.github+json")
+            .header("user-agent", "crx-dl")
+    }
+
+    /// Sends a request, failing on unsuccessful statuses.
+    fn send(&self, request: RequestBuilder, action: &str) -> Result<Response, Error> {
+        let response = self.authorize(request).send()?;
+        if !response.status().is_success() {
+            return Err(Error::other(format!("github {} failed with status {}", action, response.status())));
+        }
+        Ok(response)
+    }
+
+    /// Looks up the release, creating it if it doesn't exist.
+    fn release(&mut self) -> Result<&Value, Error> {
+        if self.release.is_none() {
+            let repo = format!("{}/repos/{}/{}", self.api_url.trim_end_matches('/'), self.owner, self.repo);
+            let response = self.authorize(self.client.get(format!("{}/releases/tags/{}", repo, self.tag)))
+                .send()
+                ?;
+            let response = match response.status() {
+                StatusCode::NOT_FOUND => {
+                    let body = json!({ "tag_name": self.tag, "name": self.tag });
+                    self.send(self.client.post(format!("{}/releases", repo)).json(&body), "release creation")?
+                },
+                status if status.is_success() => response,
+                status => return Err(Error::other(format!("github release lookup failed with status {}", status))),
+            };
+            self.release = Some(response.json()?);
+        }
+        Ok(self.release.as_ref().unwrap())
+    }
+
+    /// Uploads a single asset, replacing any with the same name.
+    fn upload(&mut self, name: &str, data: &[u8]) -> Result<(), Error> {
+        let release = self.release()?;
+        let invalid = || Error::new(ErrorKind::InvalidData, "malformed github release");
+
+        // The upload url is a template, e.g. `.../assets{?name,label}`
+        let upload_url = release["upload_url"].as_str().ok_or_else(invalid)?;
+        let upload_url = upload_url.split('{').next().unwrap_or(upload_url).to_string();
+        let existing = release["assets"]
+            .as_array()
+            .and_then(|x| x.iter().find(|x| x["name"] == name))
+            .and_then(|x| x["url"].as_str())
+            .map(str::to_string);
+
+        // Replace
+        if let Some(url) = existing {
+            self.send(self.client.delete(url), "asset deletion")?;
+        }
+        let response = self.send(
+            self.client
+                .post(upload_url)
+                .query(&[("name", name)])
+                .header("content-type", "application/octet-stream")
+                .body(data.to_vec()),
+            "asset upload",
+        )?;
+
+        // Keep the asset list current, so re-uploads replace it
+        let asset: Value = response.json()?;
+        if let Some(Value::Array(assets)) = self.release.as_mut().map(|x| &mut x["assets"]) {
+            assets.retain(|x| x["name"] != name);
+            assets.push(asset);
+        }
+        Ok(())
+    }
+
+    /// Uploads a combined `SHA256SUMS` asset covering everything stored so far.
+    pub fn upload_sha256sums(&mut self) -> Result<(), Error> {
+        self.upload(SHA256SUMS_NAME, self.sha256sums.to_string().as_bytes())
+    }
+}
+impl Sink for GitHubReleaseSink {
+    fn put(&mut self, key: &str, data: &[u8]) -> Result<(), Error> {
+        let name = Self::asset_name(key);
+        self.upload(&name, data)?;
+        if self.sha256_sidecars {
+            let line = format!("{}  {}\n", crate::sha256_hex(data), name);
+            self.upload(&format!("{}.sha256", name), line.as_bytes())?;
+        }
+        self.sha256sums.add(&name, data);
+        Ok(())
+    }
+}
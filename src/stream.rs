@@ -0,0 +1,152 @@
+// Dependencies
+use std::{fs::File, io::{self, BufReader, BufWriter, Read, Write}, path::Path};
+use crate::{check_zip, header::DEFAULT_MAX_HEADER_SIZE, CrxError, CrxVersion, Error};
+
+/// Converts a CRX to a ZIP as it is written, forwarding everything after the header to `inner`.
+/// 
+/// Only as much of the header as is needed to find the start of the ZIP is ever buffered,
+/// so a CRX can be streamed from the network straight to disk.
+/// CRX3 files wrapping another CRX, as served by addons.opera.com, are unwrapped to the innermost ZIP.
+/// Anything else that doesn't start like a ZIP after the header fails, see [`crate::ZIP_MAGIC`].
+pub struct CrxToZipWriter<W: Write> {
+    inner: W,
+    /// The start of the header, until its length is known.
+    header: Vec<u8>,
+    /// How many more header bytes to drop, once its length is known.
+    skip: Option<usize>,
+    /// The version of the header just dropped, while the start of what follows is buffered.
+    payload: Option<CrxVersion>,
+    /// Whether the CRX being read is wrapped in another, which only one level of is allowed.
+    nested: bool,
+    max_header_size: usize,
+}
+impl<W: Write> CrxToZipWriter<W> {
+    /// Wraps the writer the ZIP is written to.
+    pub fn new(inner: W) -> Self {
+        Self::with_max_header_size(inner, DEFAULT_MAX_HEADER_SIZE)
+    }
+
+    /// Wraps the writer the ZIP is written to, failing on headers larger than `max_header_size` bytes.
+    /// 
+    /// The header is skipped rather than buffered, so the limit only guards against reading garbage for too long.
+    pub fn with_max_header_size(inner: W, max_header_size: usize) -> Self {
+        Self { inner, header: Vec::new(), skip: None, payload: None, nested: false, max_header_size }
+    }
+
+    /// How many bytes of the header are needed before its length is known.
+    fn needed(&self) -> Result<usize, Error> {
+        if self.header.len() < 8 {
+            return Ok(8);
+        }
+
+        // Ensure is a CRX file
+        if &self.header[..4] != b"Cr24" {
+            return Err(CrxError::NotACrx.into());
+        }
+        match CrxVersion::try_from(u32::from_le_bytes(self.header[4..8].try_into().unwrap()))? {
+            CrxVersion::V2 => Ok(16),
+            CrxVersion::V3 => Ok(12),
+        }
+    }
+
+    /// The offset the ZIP starts at, from a complete [`CrxToZipWriter::needed`] prefix.
+    fn zip_start(&self) -> Result<usize, Error> {
+        let read_u32 = |offset: usize| u32::from_le_bytes(self.header[offset..offset + 4].try_into().unwrap()) as usize;
+        let zip_start = match self.header.len() {
+            // Public key and signature lengths
+            16 => read_u32(8).saturating_add(read_u32(12)).saturating_add(16),
+            // Protobuf header length
+            _ => read_u32(8).saturating_add(12),
+        };
+        if zip_start > self.max_header_size {
+            return Err(CrxError::HeaderTooLarge(zip_start).into());
+        }
+        Ok(zip_start)
+    }
+
+    /// Returns the writer, failing if the CRX ended before the ZIP started.
+    pub fn finish(mut self) -> Result<W, Error> {
+        // Too short to be a ZIP
+        if self.payload.is_some() && self.skip.is_none() {
+            check_zip(&self.header)?;
+        }
+        if self.skip != Some(0) {
+            return Err(CrxError::TruncatedHeader.into());
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+impl<W: Write> Write for CrxToZipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        let mut rest = buf;
+
+        // Buffer the header until its length is known
+        while self.skip.is_none() {
+            // A CRX3 header may be followed by another CRX, otherwise it has to be the ZIP
+            if let Some(version) = self.payload.filter(|_| self.header.len() >= 4) {
+                self.payload = None;
+                if version != CrxVersion::V3 || self.nested || &self.header[..4] != b"Cr24" {
+                    check_zip(&self.header)?;
+                    self.inner.write_all(&self.header)?;
+                    self.header.clear();
+                    self.skip = Some(0);
+                    break;
+                }
+                self.nested = true;
+            }
+
+            let needed = self.needed()?;
+            if self.header.len() == needed {
+                self.skip = Some(self.zip_start()? - needed);
+                break;
+            }
+            if rest.is_empty() {
+                return Ok(buf.len());
+            }
+            let take = (needed - self.header.len()).min(rest.len());
+            self.header.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+        }
+
+        // Drop the rest of the header
+        let skip = self.skip.as_mut().unwrap();
+        let dropped = (*skip).min(rest.len());
+        *skip -= dropped;
+        rest = &rest[dropped..];
+
+        // Check what follows the header, addons.opera.com wraps a CRX2 in a CRX3
+        if *skip == 0 && !self.header.is_empty() {
+            self.payload = CrxVersion::try_from(u32::from_le_bytes(self.header[4..8].try_into().unwrap())).ok();
+            self.header.clear();
+            self.skip = None;
+            return self.write(rest).map(|_| buf.len());
+        }
+
+        // Done
+        self.inner.write_all(rest)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.inner.flush()
+    }
+}
+
+/// Converts the CRX read from `reader` to a ZIP written to `writer`, see [`CrxToZipWriter`].
+/// 
+/// Only a small buffer is ever held in memory, however big the CRX. Returns the writer once the ZIP is written.
+/// Unlike [`crate::crx_to_zip`], the input has to be a CRX.
+pub fn crx_to_zip_stream<W: Write>(mut reader: impl Read, writer: W) -> Result<W, Error> {
+    let mut writer = CrxToZipWriter::new(writer);
+    std::io::copy(&mut reader, &mut writer)?;
+    writer.finish()
+}
+
+/// Converts the CRX at `input` to a ZIP at `output`, see [`crx_to_zip_stream`].
+/// 
+/// Both files are buffered, and `output` is replaced atomically, see [`crate::fs::write_atomic_with`].
+pub fn crx_to_zip_file(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<(), Error> {
+    let reader = BufReader::new(File::open(input)?);
+    crate::fs::write_atomic_with(output, |file| Ok(crx_to_zip_stream(reader, BufWriter::new(file))?.flush()?))
+}
@@ -0,0 +1,370 @@
+// Dependencies
+use std::{collections::BTreeMap, fmt, io::ErrorKind};
+use crate::{batch::{BatchError, Stage}, ChromeCRXQuery, Error, XParam};
+
+/// A `prodversion` newer than any Chrome, which the store serves everything to.
+const LATEST_PRODVERSION: &str = "9999.0.9999.0";
+
+/// How many ids are sent per request by [`ChromeCRXQuery::check_many`], keeping urls well under the limit servers accept.
+pub const UPDATECHECK_BATCH_SIZE: usize = 100;
+
+/// The result of `response=updatecheck` for a single extension.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateCheck {
+    pub app_id: String,
+    /// `ok` when an update is offered, otherwise something like `noupdate` or `error-unknownApplication`.
+    pub status: String,
+    /// Where the CRX can be downloaded from.
+    pub codebase: Option<String>,
+    pub version: Option<String>,
+    /// The hex encoded SHA-256 of the CRX.
+    pub hash_sha256: Option<String>,
+    /// The size of the CRX in bytes.
+    pub size: Option<u64>,
+}
+impl UpdateCheck {
+    /// Whether the server offered a CRX.
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok" && self.codebase.is_some()
+    }
+
+    /// Ensures `crx` has the SHA-256 the server reported, if it reported one.
+    pub fn verify(&self, crx: &[u8]) -> Result<(), HashMismatch> {
+        use sha2::{Digest, Sha256};
+        self.verify_sha256(&Sha256::digest(crx))
+    }
+
+    /// [`UpdateCheck::verify`] with the SHA-256 of the CRX, e.g. one hashed while it was streamed.
+    pub fn verify_sha256(&self, sha256: &[u8]) -> Result<(), HashMismatch> {
+        let Some(expected) = &self.hash_sha256 else {
+            return Ok(());
+        };
+        let actual = crate::hex(sha256);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(HashMismatch { app_id: self.app_id.clone(), expected: expected.clone(), actual });
+        }
+        Ok(())
+    }
+}
+
+/// Parses an Omaha updatecheck XML response, one entry per `<app>`.
+pub fn parse_updatecheck(xml: &str) -> Result<Vec<UpdateCheck>, Error> {
+    let document = roxmltree::Document::parse(xml)
+        .map_err(|x| Error::new(ErrorKind::InvalidData, x))?;
+
+    Ok(
+        document
+            .descendants()
+            .filter(|x| x.has_tag_name("app"))
+            .map(|app| {
+                let updatecheck = app.children().find(|x| x.has_tag_name("updatecheck"));
+                let attribute = |name| updatecheck.and_then(|x| x.attribute(name)).map(str::to_string);
+                let app_status = app.attribute("status").unwrap_or_default();
+                UpdateCheck {
+                    app_id: app.attribute("appid").unwrap_or_default().to_string(),
+                    status: match app_status {
+                        "ok" | "" => attribute("status").unwrap_or_default(),
+                        x => x.to_string(),
+                    },
+                    codebase: attribute("codebase"),
+                    version: attribute("version"),
+                    hash_sha256: attribute("hash_sha256"),
+                    size: attribute("size").and_then(|x| x.parse().ok()),
+                }
+            })
+            .collect()
+    )
+}
+
+/// Why the store does not serve an extension, as far as can be told from its updatecheck status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum NotServedReason {
+    /// The store doesn't know the id, it may never have existed.
+    NotFound,
+    /// The store knows the id but offers nothing, usually because it was taken down or unlisted.
+    Removed,
+    /// The store would serve it to a newer Chrome, raise `prodversion`.
+    ProdVersionTooOld,
+    /// Anything else, see [`NotServed::status`].
+    Other,
+}
+impl NotServedReason {
+    /// Classifies an updatecheck status.
+    /// 
+    /// `noupdate` is also what a too old `prodversion` gets, which only asking again can tell apart.
+    pub fn from_status(status: &str) -> Self {
+        match status {
+            "error-unknownApplication" | "error-invalidAppId" | "missing" => Self::NotFound,
+            "noupdate" => Self::Removed,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Returned when the store does not serve an extension, with the reason it gave.
+/// 
+/// Returned as [`Error::NotServed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotServed {
+    pub app_id: String,
+    /// The updatecheck status, e.g. `noupdate` or `error-unknownApplication`.
+    pub status: String,
+    pub reason: NotServedReason,
+}
+impl fmt::Display for NotServed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "extension {} is not served ({}): {}", self.app_id, self.reason, self.status)
+    }
+}
+impl std::error::Error for NotServed {}
+
+/// Returned when a downloaded CRX does not have the `hash_sha256` its updatecheck reported, see [`UpdateCheck::verify`].
+/// 
+/// Returned as [`Error::HashMismatch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashMismatch {
+    pub app_id: String,
+    /// The hex encoded SHA-256 the server reported.
+    pub expected: String,
+    /// The hex encoded SHA-256 of what was downloaded.
+    pub actual: String,
+}
+impl fmt::Display for HashMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "extension {} does not match its reported sha256: expected {}, got {}", self.app_id, self.expected, self.actual)
+    }
+}
+impl std::error::Error for HashMismatch {}
+
+/// Picks the result for `id` out of an updatecheck response, and turns anything but an offered CRX into [`NotServed`].
+pub(crate) fn offered(xml: &[u8], id: &str) -> Result<UpdateCheck, Error> {
+    let checks = parse_updatecheck(&String::from_utf8_lossy(xml))?;
+    let check = checks.into_iter().find(|x| x.app_id == id);
+    match check {
+        Some(check) if check.codebase.is_some() => Ok(check),
+        check => Err(not_served(id, check).into()),
+    }
+}
+
+impl ChromeCRXQuery<'_> {
+    /// Sends the query as an updatecheck, then downloads the CRX from the codebase it offers.
+    /// 
+    /// Unlike following the redirect, this also gives the version, hash and size the store reports.
+    /// Fails with a [`HashMismatch`] if the CRX does not have the reported hash,
+    /// or [`VerifyError::IdMismatch`](crate::VerifyError::IdMismatch) if it is of another extension, unless `verify_id` is unset.
+    /// For a blocking version, use [`download_with_updatecheck_blocking`].
+    pub async fn download_with_updatecheck(&self) -> Result<(UpdateCheck, Vec<u8>), Error> {
+        let client = crate::shared_client();
+        let query = ChromeCRXQuery { response: "updatecheck", ..self.clone() };
+        let xml = query.request(client).send().await?.bytes().await?;
+        let check = offered(&xml, self.x)?;
+
+        let codebase = check.codebase.as_deref().unwrap_or_default();
+        let response = client.get(codebase).send().await.and_then(|x| x.error_for_status())?;
+        let crx = response.bytes().await?.to_vec();
+        check.verify(&crx)?;
+        Ok((check, self.check_id(crx)?))
+    }
+
+    /// Sends the query as an updatecheck, then downloads the CRX from the codebase it offers.
+    /// 
+    /// Unlike following the redirect, this also gives the version, hash and size the store reports.
+    /// Fails with a [`HashMismatch`] if the CRX does not have the reported hash,
+    /// or [`VerifyError::IdMismatch`](crate::VerifyError::IdMismatch) if it is of another extension, unless `verify_id` is unset.
+    /// For a async version, use [`download_with_updatecheck`].
+    pub fn download_with_updatecheck_blocking(&self) -> Result<(UpdateCheck, Vec<u8>), Error> {
+        let client = crate::shared_blocking_client();
+        let query = ChromeCRXQuery { response: "updatecheck", ..self.clone() };
+        let xml = query.request_blocking(client).send()?.bytes()?;
+        let check = offered(&xml, self.x)?;
+
+        let codebase = check.codebase.as_deref().unwrap_or_default();
+        let response = client.get(codebase).send().and_then(|x| x.error_for_status())?;
+        let crx = response.bytes()?.to_vec();
+        check.verify(&crx)?;
+        Ok((check, self.check_id(crx)?))
+    }
+
+    /// Downloads the CRX after a redirect query came back empty or failed, by asking for an updatecheck instead.
+    /// Fails with a [`NotServed`] if no CRX is offered, or a [`HashMismatch`] if it does not have the reported hash.
+    pub(crate) async fn download_unserved(&self, client: &reqwest::Client) -> Result<Vec<u8>, Error> {
+        let updatecheck = ChromeCRXQuery { response: "updatecheck", ..self.clone() };
+        let xml = updatecheck.request(client).send().await?.bytes().await?;
+        let check = match offered(&xml, self.x) {
+            Ok(check) => check,
+            // Ask again as the newest Chrome, to see whether the version is the problem
+            Err(error) if maybe_too_old(&error) && self.prodversion != LATEST_PRODVERSION => {
+                let latest = ChromeCRXQuery { prodversion: LATEST_PRODVERSION, ..updatecheck };
+                let xml = latest.request(client).send().await?.bytes().await?;
+                return Err(if offered(&xml, self.x).is_ok() { too_old(error) } else { error });
+            },
+            Err(error) => return Err(error),
+        };
+
+        let codebase = check.codebase.as_deref().unwrap_or_default();
+        let response = client.get(codebase).send().await.and_then(|x| x.error_for_status())?;
+        let crx = response.bytes().await?.to_vec();
+        check.verify(&crx)?;
+        Ok(crx)
+    }
+
+    /// Downloads the CRX after a redirect query came back empty or failed, by asking for an updatecheck instead.
+    /// Fails with a [`NotServed`] if no CRX is offered, or a [`HashMismatch`] if it does not have the reported hash.
+    pub(crate) fn download_unserved_blocking(&self, client: &reqwest::blocking::Client) -> Result<Vec<u8>, Error> {
+        let updatecheck = ChromeCRXQuery { response: "updatecheck", ..self.clone() };
+        let xml = updatecheck.request_blocking(client).send()?.bytes()?;
+        let check = match offered(&xml, self.x) {
+            Ok(check) => check,
+            // Ask again as the newest Chrome, to see whether the version is the problem
+            Err(error) if maybe_too_old(&error) && self.prodversion != LATEST_PRODVERSION => {
+                let latest = ChromeCRXQuery { prodversion: LATEST_PRODVERSION, ..updatecheck };
+                let xml = latest.request_blocking(client).send()?.bytes()?;
+                return Err(if offered(&xml, self.x).is_ok() { too_old(error) } else { error });
+            },
+            Err(error) => return Err(error),
+        };
+
+        let codebase = check.codebase.as_deref().unwrap_or_default();
+        let response = client.get(codebase).send().and_then(|x| x.error_for_status())?;
+        let crx = response.bytes()?.to_vec();
+        check.verify(&crx)?;
+        Ok(crx)
+    }
+
+    /// The query parameters for an updatecheck of every id in `ids`, each as its own `x`.
+    fn batch_params(&self, ids: &[&str]) -> Vec<(String, String)> {
+        let mut params = ChromeCRXQuery { response: "updatecheck", ..self.clone() }.to_vec();
+        params.retain(|x| x.0 != "x");
+        params.extend(ids.iter().map(|id| (String::from("x"), XParam { id, version: None, ..self.x_param() }.to_string())));
+        params
+    }
+
+    /// Checks many extensions at once, in batches of [`UPDATECHECK_BATCH_SIZE`]. `x` and `version` are ignored.
+    /// 
+    /// Every id the server answered for is in the map, including ones it doesn't serve, see [`UpdateCheck::is_ok`].
+    /// For a blocking version, use [`check_many_blocking`].
+    pub async fn check_many(&self, ids: &[&str]) -> Result<BTreeMap<String, UpdateCheck>, Error> {
+        let client = crate::shared_client();
+        let mut checks = BTreeMap::new();
+        for batch in ids.chunks(UPDATECHECK_BATCH_SIZE) {
+            let mut request = client.get(self.endpoint).query(&self.batch_params(batch));
+            for (name, value) in self.headers() {
+                request = request.header(name, value);
+            }
+            let response = request.send().await.and_then(|x| x.error_for_status())?;
+            let xml = response.text().await?;
+            checks.extend(parse_updatecheck(&xml)?.into_iter().map(|x| (x.app_id.clone(), x)));
+        }
+        Ok(checks)
+    }
+
+    /// Checks many extensions at once, in batches of [`UPDATECHECK_BATCH_SIZE`]. `x` and `version` are ignored.
+    /// 
+    /// Every id the server answered for is in the map, including ones it doesn't serve, see [`UpdateCheck::is_ok`].
+    /// For a async version, use [`check_many`].
+    pub fn check_many_blocking(&self, ids: &[&str]) -> Result<BTreeMap<String, UpdateCheck>, Error> {
+        let client = crate::shared_blocking_client();
+        let mut checks = BTreeMap::new();
+        for batch in ids.chunks(UPDATECHECK_BATCH_SIZE) {
+            let mut request = client.get(self.endpoint).query(&self.batch_params(batch));
+            for (name, value) in self.headers() {
+                request = request.header(name, value);
+            }
+            let response = request.send().and_then(|x| x.error_for_status())?;
+            let xml = response.text()?;
+            checks.extend(parse_updatecheck(&xml)?.into_iter().map(|x| (x.app_id.clone(), x)));
+        }
+        Ok(checks)
+    }
+
+    /// Ensures a CRX of `download_many` has the reported hash, and is of `id` if `verify_id` is set.
+    fn verify_batch(&self, check: &UpdateCheck, id: &str, crx: Vec<u8>) -> Result<Vec<u8>, BatchError> {
+        check.verify(&crx)
+            .map_err(Error::from)
+            .and_then(|_| if self.verify_id { Ok(crate::verify_extension_id(&crx, id)?) } else { Ok(()) })
+            .map(|_| crx)
+            .map_err(|x| BatchError::new(id, Stage::Verify, x))
+    }
+
+    /// Downloads many extensions, looking them all up with [`ChromeCRXQuery::check_many`] first. `x` and `version` are ignored.
+    /// 
+    /// Only fails as a whole if the lookup does, otherwise each id has its own outcome.
+    /// For a blocking version, use [`download_many_blocking`].
+    pub async fn download_many(&self, ids: &[&str]) -> Result<BTreeMap<String, Result<Vec<u8>, BatchError>>, Error> {
+        let mut checks = self.check_many(ids).await?;
+        let client = crate::shared_client();
+        let mut crxs = BTreeMap::new();
+        for id in ids {
+            let result = match checks.remove(*id) {
+                Some(check @ UpdateCheck { codebase: Some(_), .. }) => async {
+                    let codebase = check.codebase.as_deref().unwrap_or_default();
+                    let response = client.get(codebase).send().await.and_then(|x| x.error_for_status())?;
+                    Ok(response.bytes().await?.to_vec())
+                }.await
+                    .map_err(|x: reqwest::Error| BatchError::new(*id, Stage::Download, Error::other(x)))
+                    .and_then(|crx| self.verify_batch(&check, id, crx)),
+                check => Err(BatchError::new(*id, Stage::UpdateCheck, not_served(id, check))),
+            };
+            crxs.insert(id.to_string(), result);
+        }
+        Ok(crxs)
+    }
+
+    /// Downloads many extensions, looking them all up with [`ChromeCRXQuery::check_many_blocking`] first. `x` and `version` are ignored.
+    /// 
+    /// Only fails as a whole if the lookup does, otherwise each id has its own outcome.
+    /// For a async version, use [`download_many`].
+    pub fn download_many_blocking(&self, ids: &[&str]) -> Result<BTreeMap<String, Result<Vec<u8>, BatchError>>, Error> {
+        let mut checks = self.check_many_blocking(ids)?;
+        let client = crate::shared_blocking_client();
+        let mut crxs = BTreeMap::new();
+        for id in ids {
+            let result = match checks.remove(*id) {
+                Some(check @ UpdateCheck { codebase: Some(_), .. }) => client
+                    .get(check.codebase.as_deref().unwrap_or_default())
+                    .send()
+                    .and_then(|x| x.error_for_status())
+                    .and_then(|x| x.bytes())
+                    .map(|x| x.to_vec())
+                    .map_err(|x| BatchError::new(*id, Stage::Download, Error::other(x)))
+                    .and_then(|crx| self.verify_batch(&check, id, crx)),
+                check => Err(BatchError::new(*id, Stage::UpdateCheck, not_served(id, check))),
+            };
+            crxs.insert(id.to_string(), result);
+        }
+        Ok(crxs)
+    }
+}
+
+/// The [`NotServed`] for an id whose updatecheck offered no CRX.
+fn not_served(id: &str, check: Option<UpdateCheck>) -> NotServed {
+    let status = check.map(|x| x.status).unwrap_or_else(|| String::from("missing"));
+    NotServed {
+        app_id: id.to_string(),
+        reason: NotServedReason::from_status(&status),
+        status,
+    }
+}
+
+/// The [`NotServed`] inside `error`, if it is one.
+fn as_not_served(error: &Error) -> Option<&NotServed> {
+    match error {
+        Error::NotServed(x) => Some(x),
+        _ => None,
+    }
+}
+
+/// Whether `error` is worth asking again with the newest `prodversion` for, see [`NotServedReason::from_status`].
+fn maybe_too_old(error: &Error) -> bool {
+    as_not_served(error).is_some_and(|x| x.reason == NotServedReason::Removed)
+}
+
+/// Turns `error` into a [`NotServedReason::ProdVersionTooOld`].
+fn too_old(error: Error) -> Error {
+    match as_not_served(&error) {
+        Some(not_served) => NotServed { reason: NotServedReason::ProdVersionTooOld, ..not_served.clone() }.into(),
+        None => error,
+    }
+}
+
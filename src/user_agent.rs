@@ -0,0 +1,43 @@
+// Dependencies
+use std::borrow::Cow;
+use crate::{Architecture, ChromeCRXQuery, OperatingSystem};
+
+impl ChromeCRXQuery<'_> {
+    /// A `User-Agent` a real Chrome on `os` and `arch` would send, claiming the major version of `prodversion`.
+    /// 
+    /// Follows Chrome's reduced format, where the minor version numbers are always zero.
+    pub fn chrome_user_agent(&self) -> String {
+        let platform = match (&self.os, &self.arch) {
+            (OperatingSystem::Windows, Architecture::Intel32) => "Windows NT 10.0",
+            (OperatingSystem::Windows, _) => "Windows NT 10.0; Win64; x64",
+            (OperatingSystem::Linux, Architecture::ARM) => "X11; Linux aarch64",
+            (OperatingSystem::Linux, Architecture::Intel32) => "X11; Linux i686",
+            (OperatingSystem::Linux, Architecture::AMD64) => "X11; Linux x86_64",
+            // Even Apple Silicon Macs claim to be Intel
+            (OperatingSystem::MacOS, _) => "Macintosh; Intel Mac OS X 10_15_7",
+            (OperatingSystem::ChromeOS, Architecture::ARM) => "X11; CrOS aarch64 14541.0.0",
+            (OperatingSystem::ChromeOS, _) => "X11; CrOS x86_64 14541.0.0",
+            (OperatingSystem::BSD, Architecture::ARM) => "X11; OpenBSD arm64",
+            (OperatingSystem::BSD, Architecture::Intel32) => "X11; OpenBSD i386",
+            (OperatingSystem::BSD, Architecture::AMD64) => "X11; OpenBSD amd64",
+            (OperatingSystem::Android, _) => "Linux; Android 10; K",
+        };
+        let major = self.prodversion.split('.').next().unwrap_or_default();
+        let mobile = if self.os == OperatingSystem::Android { "Mobile " } else { "" };
+        format!(
+            "Mozilla/5.0 ({}) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{}.0.0.0 {}Safari/537.36",
+            platform, major, mobile
+        )
+    }
+
+    /// The `User-Agent` header sent with the query, if any.
+    /// 
+    /// That is [`ChromeCRXQuery::user_agent`], or [`ChromeCRXQuery::chrome_user_agent`] if [`ChromeCRXQuery::synthesize_user_agent`] is set.
+    pub fn effective_user_agent(&self) -> Option<Cow<'_, str>> {
+        match self.user_agent {
+            Some(user_agent) => Some(Cow::Borrowed(user_agent)),
+            None if self.synthesize_user_agent => Some(Cow::Owned(self.chrome_user_agent())),
+            None => None,
+        }
+    }
+}
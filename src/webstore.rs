@@ -0,0 +1,242 @@
+// Dependencies
+use std::io::ErrorKind;
+use crate::{is_extension_id, shared_blocking_client, shared_client, Error};
+
+/// The root of the Chrome Web Store.
+pub const CHROME_WEB_STORE_URL: &str = "https://chromewebstore.google.com";
+
+/// What the Chrome Web Store lists about an item, scraped from its detail page.
+/// 
+/// The page has no stable format, so anything that couldn't be found is `None`.
+/// For the version actually served, prefer an updatecheck, see [`crate::updatecheck`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WebStoreMetadata {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// As displayed, e.g. `November 23, 2024`.
+    pub updated: Option<String>,
+    pub users: Option<u64>,
+    /// Out of 5.
+    pub rating: Option<f64>,
+    pub rating_count: Option<u64>,
+}
+
+/// An item found by [`WebStore::search`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub id: String,
+    /// The name as listed, if the result showed one.
+    pub name: Option<String>,
+    /// The detail page of the item.
+    pub url: String,
+}
+
+/// Looks things up on the Chrome Web Store.
+#[derive(Debug, Clone)]
+pub struct WebStore<'a> {
+    /// The store root, [`CHROME_WEB_STORE_URL`] by default.
+    pub endpoint: &'a str,
+    /// The language pages are requested in, sent as `hl`. Scraping expects English.
+    pub language: &'a str,
+    /// Overrides the `User-Agent` header, the reqwest default is used otherwise.
+    pub user_agent: Option<&'a str>,
+}
+impl Default for WebStore<'_> {
+    fn default() -> Self {
+        Self { endpoint: CHROME_WEB_STORE_URL, language: "en", user_agent: None }
+    }
+}
+impl WebStore<'_> {
+    /// The url of the detail page for `id`.
+    pub fn detail_url(&self, id: &str) -> String {
+        format!("{}/detail/{}?hl={}", self.endpoint.trim_end_matches('/'), id, self.language)
+    }
+
+    /// Fetches the listing of `id`, see [`WebStoreMetadata`].
+    /// 
+    /// For a blocking version, use [`metadata_blocking`].
+    pub async fn metadata(&self, id: &str) -> Result<WebStoreMetadata, Error> {
+        check_id(id)?;
+        let mut request = shared_client().get(self.detail_url(id));
+        if let Some(user_agent) = self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        let response = request.send().await.and_then(|x| x.error_for_status())?;
+        Ok(parse_webstore_metadata(id, &response.text().await?))
+    }
+
+    /// Fetches the listing of `id`, see [`WebStoreMetadata`].
+    /// 
+    /// For a async version, use [`metadata`].
+    pub fn metadata_blocking(&self, id: &str) -> Result<WebStoreMetadata, Error> {
+        check_id(id)?;
+        let mut request = shared_blocking_client().get(self.detail_url(id));
+        if let Some(user_agent) = self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        let response = request.send().and_then(|x| x.error_for_status())?;
+        Ok(parse_webstore_metadata(id, &response.text()?))
+    }
+
+    /// The url of the search page for `query`.
+    pub fn search_url(&self, query: &str) -> Result<reqwest::Url, Error> {
+        let mut url = reqwest::Url::parse(self.endpoint).map_err(Error::other)?;
+        url.path_segments_mut()
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid web store endpoint"))?
+            .pop_if_empty()
+            .extend(["search", query]);
+        url.query_pairs_mut().append_pair("hl", self.language);
+        Ok(url)
+    }
+
+    /// Searches the store by name, returning at most `limit` results in the order the store ranks them.
+    /// 
+    /// Only the first page is available, the store loads more with scripts.
+    /// For a blocking version, use [`search_blocking`].
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>, Error> {
+        let mut request = shared_client().get(self.search_url(query)?);
+        if let Some(user_agent) = self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        let response = request.send().await.and_then(|x| x.error_for_status())?;
+        let html = response.text().await?;
+        Ok(self.parse_search(&html, limit))
+    }
+
+    /// Searches the store by name, returning at most `limit` results in the order the store ranks them.
+    /// 
+    /// Only the first page is available, the store loads more with scripts.
+    /// For a async version, use [`search`].
+    pub fn search_blocking(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>, Error> {
+        let mut request = shared_blocking_client().get(self.search_url(query)?);
+        if let Some(user_agent) = self.user_agent {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        let response = request.send().and_then(|x| x.error_for_status())?;
+        let html = response.text()?;
+        Ok(self.parse_search(&html, limit))
+    }
+
+    /// Scrapes a search page, picking out every link to a detail page.
+    pub fn parse_search(&self, html: &str, limit: usize) -> Vec<SearchResult> {
+        let mut results: Vec<SearchResult> = Vec::new();
+        let mut rest = html;
+        while results.len() < limit {
+            let Some(start) = rest.find("href=\"") else {
+                break;
+            };
+            rest = &rest[start + 6..];
+            let end = rest.find('"').unwrap_or(rest.len());
+            let Some(id) = detail_id(&rest[..end]).filter(|id| results.iter().all(|x| x.id != *id)) else {
+                continue;
+            };
+
+            let anchor = &rest[end..];
+            let anchor = &anchor[anchor.find('>').map_or(0, |x| x + 1)..anchor.find("</a>").unwrap_or(anchor.len())];
+            results.push(SearchResult {
+                id: id.to_string(),
+                name: html_text(anchor).into_iter().next(),
+                url: format!("{}/detail/{}", self.endpoint.trim_end_matches('/'), id),
+            });
+        }
+        results
+    }
+}
+
+/// The id in a link to a detail page, which look like `./detail/<slug>/<id>`.
+fn detail_id(href: &str) -> Option<&str> {
+    let path = href.split(['?', '#']).next()?;
+    let id = path.split_once("detail/")?.1.rsplit('/').next()?;
+    is_extension_id(id).then_some(id)
+}
+
+/// Fails unless `id` is an extension id, which keeps it from escaping the url path.
+fn check_id(id: &str) -> Result<(), Error> {
+    if !is_extension_id(id) {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("not an extension id: {}", id)));
+    }
+    Ok(())
+}
+
+/// Scrapes a detail page, as served by [`WebStore::metadata`].
+pub fn parse_webstore_metadata(id: &str, html: &str) -> WebStoreMetadata {
+    let text = html_text(html);
+    let after = |label: &str| text
+        .iter()
+        .position(|x| x.eq_ignore_ascii_case(label))
+        .and_then(|i| text.get(i + 1))
+        .cloned();
+
+    WebStoreMetadata {
+        id: id.to_string(),
+        name: meta_content(html, "og:title").map(|x| x.trim_end_matches(" - Chrome Web Store").to_string()),
+        version: after("Version"),
+        updated: after("Updated"),
+        users: text.iter().find_map(|x| parse_count(x.strip_suffix(" users").or_else(|| x.strip_suffix(" user"))?)),
+        rating: text.iter().find_map(|x| x.split_once(" out of 5")?.0.rsplit(' ').next()?.parse().ok()),
+        rating_count: text.iter().find_map(|x| {
+            let x = x.trim_matches(['(', ')']);
+            parse_count(x.strip_suffix(" ratings").or_else(|| x.strip_suffix(" rating"))?)
+        }),
+    }
+}
+
+/// Parses counts like `10,000,000+` or `2K`.
+pub(crate) fn parse_count(count: &str) -> Option<u64> {
+    let count = count.trim().trim_end_matches('+').replace(',', "");
+    let (number, multiplier) = match count.chars().last()? {
+        'K' | 'k' => (&count[..count.len() - 1], 1e3),
+        'M' | 'm' => (&count[..count.len() - 1], 1e6),
+        _ => (count.as_str(), 1.0),
+    };
+    Some((number.parse::<f64>().ok()? * multiplier) as u64)
+}
+
+/// The content of `<meta property="{property}" content="...">`.
+pub(crate) fn meta_content(html: &str, property: &str) -> Option<String> {
+    let start = html.find(&format!("property=\"{}\"", property))?;
+    let tag_start = html[..start].rfind('<')?;
+    let tag = &html[tag_start..tag_start + html[tag_start..].find('>')?];
+    let content = &tag[tag.find("content=\"")? + 9..];
+    Some(decode_entities(&content[..content.find('"')?]))
+}
+
+/// The text between the tags of `html`, one trimmed entry per run of text, skipping scripts and styles.
+pub(crate) fn html_text(html: &str) -> Vec<String> {
+    let mut text = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let run = decode_entities(rest[..start].trim());
+        if !run.is_empty() {
+            text.push(run);
+        }
+
+        // Skip the tag, and the contents of scripts and styles
+        rest = &rest[start..];
+        let lower = rest.get(..7).unwrap_or_default().to_ascii_lowercase();
+        let end = if lower.starts_with("<script") {
+            rest.find("</script>").map(|x| x + 9)
+        } else if lower.starts_with("<style") {
+            rest.find("</style>").map(|x| x + 8)
+        } else {
+            rest.find('>').map(|x| x + 1)
+        };
+        rest = &rest[end.unwrap_or(rest.len())..];
+    }
+    let run = decode_entities(rest.trim());
+    if !run.is_empty() {
+        text.push(run);
+    }
+    text
+}
+
+/// Decodes the HTML entities that show up in store pages.
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
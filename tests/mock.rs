@@ -0,0 +1,194 @@
+// Dependencies
+use crx_dl::{
+    crx_extension_id,
+    mock::{crx3, MockResponse, MockUpdateServer, VcrServer},
+    ChromeCRXQuery, Downloader, Error, OperaCRXQuery, VerifyError,
+};
+
+/// A mock server serving `response` for the id of the CRX, along with that CRX and id.
+fn serve(response: impl FnOnce(Vec<u8>) -> MockResponse) -> (MockUpdateServer, Vec<u8>, String) {
+    let crx = crx3(b"payload");
+    let id = crx_extension_id(&crx).unwrap();
+    let server = MockUpdateServer::start().unwrap();
+    server.serve(&id, response(crx.clone()));
+    (server, crx, id)
+}
+
+#[test]
+fn downloads_redirected_crx() {
+    let (server, crx, id) = serve(|crx| MockResponse::Crx { version: "1.0".into(), crx });
+    let url = server.url();
+    let query = ChromeCRXQuery { endpoint: &url, x: &id, ..Default::default() };
+
+    assert_eq!(query.download_blocking().unwrap(), crx);
+    assert_eq!(Downloader::default().download_blocking(&query).unwrap(), crx);
+    assert!(server.requests().iter().all(|x| !x.contains("response=updatecheck")));
+}
+
+#[tokio::test]
+async fn downloads_redirected_crx_async() {
+    let (server, crx, id) = serve(|crx| MockResponse::Crx { version: "1.0".into(), crx });
+    let url = server.url();
+    let query = ChromeCRXQuery { endpoint: &url, x: &id, ..Default::default() };
+
+    assert_eq!(query.download().await.unwrap(), crx);
+    assert_eq!(Downloader::default().download(&query).await.unwrap(), crx);
+}
+
+#[test]
+fn falls_back_to_updatecheck_on_204() {
+    let (server, crx, id) = serve(|crx| MockResponse::Unredirected { version: "1.0".into(), crx, hash_sha256: None });
+    let url = server.url();
+    let query = ChromeCRXQuery { endpoint: &url, x: &id, ..Default::default() };
+
+    assert_eq!(query.download_blocking().unwrap(), crx);
+    assert_eq!(Downloader::default().download_blocking(&query).unwrap(), crx);
+    assert!(server.requests().iter().any(|x| x.contains("response=updatecheck")));
+    assert!(server.requests().iter().any(|x| x.starts_with(&format!("GET /crx/{}.crx", id))));
+}
+
+#[test]
+fn reports_unserved_extensions() {
+    let (server, _, id) = serve(|_| MockResponse::NoContent);
+    let url = server.url();
+    let query = ChromeCRXQuery { endpoint: &url, x: &id, ..Default::default() };
+
+    for error in [query.download_blocking().unwrap_err(), Downloader::default().download_blocking(&query).unwrap_err()] {
+        let Error::NotServed(not_served) = error else { panic!("expected NotServed, got {:?}", error) };
+        assert_eq!((not_served.app_id.as_str(), not_served.status.as_str()), (id.as_str(), "noupdate"));
+    }
+}
+
+#[test]
+fn rejects_crx_with_wrong_hash() {
+    let (server, _, id) = serve(|crx| MockResponse::Unredirected { version: "1.0".into(), crx, hash_sha256: Some("00".repeat(32)) });
+    let url = server.url();
+    let query = ChromeCRXQuery { endpoint: &url, x: &id, ..Default::default() };
+
+    for error in [query.download_blocking().unwrap_err(), Downloader::default().download_blocking(&query).unwrap_err()] {
+        assert!(matches!(error, Error::HashMismatch(x) if x.expected == "00".repeat(32)));
+    }
+}
+
+#[test]
+fn rejects_crx_of_another_extension() {
+    let other = "a".repeat(32);
+    let server = MockUpdateServer::start().unwrap();
+    server.serve(&other, MockResponse::Crx { version: "1.0".into(), crx: crx3(b"payload") });
+    let url = server.url();
+    let query = ChromeCRXQuery { endpoint: &url, x: &other, ..Default::default() };
+
+    for error in [query.download_blocking().unwrap_err(), Downloader::default().download_blocking(&query).unwrap_err()] {
+        assert!(matches!(error, Error::SignatureInvalid(VerifyError::IdMismatch { .. })));
+    }
+    let unchecked = ChromeCRXQuery { verify_id: false, ..query };
+    assert!(unchecked.download_blocking().is_ok());
+}
+
+#[test]
+fn checks_many_at_once() {
+    let (server, crx, id) = serve(|crx| MockResponse::Crx { version: "1.0".into(), crx });
+    let unserved = "a".repeat(32);
+    let url = server.url();
+    let query = ChromeCRXQuery { endpoint: &url, ..Default::default() };
+
+    let checks = query.check_many_blocking(&[&id, &unserved]).unwrap();
+    assert!(checks[&id].is_ok());
+    assert_eq!(checks[&id].version.as_deref(), Some("1.0"));
+    assert_eq!(checks[&id].size, Some(crx.len() as u64));
+    assert!(!checks[&unserved].is_ok());
+    assert_eq!(server.requests().len(), 1);
+
+    let crxs = query.download_many_blocking(&[&id, &unserved]).unwrap();
+    assert_eq!(crxs[&id].as_ref().unwrap(), &crx);
+    assert!(crxs[&unserved].is_err());
+}
+
+#[test]
+fn resumes_only_the_same_crx() {
+    let (server, crx, id) = serve(|crx| MockResponse::Crx { version: "1.0".into(), crx });
+    let url = server.url();
+    let query = ChromeCRXQuery { endpoint: &url, x: &id, ..Default::default() };
+    let downloader = Downloader::default();
+    let path = std::env::temp_dir().join(format!("crx-dl-resume-{}.crx", std::process::id()));
+    let truncate = || std::fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(20).unwrap();
+
+    assert_eq!(downloader.download_resume_blocking(&query, &path).unwrap().status, 200);
+    truncate();
+    assert_eq!(downloader.download_resume_blocking(&query, &path).unwrap().status, 206);
+    assert_eq!(std::fs::read(&path).unwrap(), crx);
+
+    // A new version must not be spliced onto the old one
+    let newer = crx3(b"newer payload");
+    server.serve(&id, MockResponse::Crx { version: "2.0".into(), crx: newer.clone() });
+    truncate();
+    assert_eq!(downloader.download_resume_blocking(&query, &path).unwrap().status, 200);
+    assert_eq!(std::fs::read(&path).unwrap(), newer);
+
+    std::fs::remove_file(crx_dl::validators_path(&path)).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn vcr_replays_by_request_headers() {
+    let (server, crx, id) = serve(|crx| MockResponse::Crx { version: "1.0".into(), crx });
+    let dir = std::env::temp_dir();
+    let (cassette, path) = (dir.join(format!("crx-dl-vcr-{}.json", std::process::id())), dir.join(format!("crx-dl-vcr-{}.crx", std::process::id())));
+    let downloader = Downloader::default();
+    let truncate = || std::fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(20).unwrap();
+
+    // Record a whole download, then a resumed one
+    let upstream = server.url();
+    let recorder = VcrServer::record(&cassette).unwrap();
+    let url = recorder.url(&upstream);
+    let query = ChromeCRXQuery { endpoint: &url, x: &id, ..Default::default() };
+    assert_eq!(downloader.download_resume_blocking(&query, &path).unwrap().status, 200);
+    truncate();
+    assert_eq!(downloader.download_resume_blocking(&query, &path).unwrap().status, 206);
+    drop((recorder, server));
+
+    // The resumed one is found by its Range, even though the whole one was recorded first
+    let player = VcrServer::replay(&cassette).unwrap();
+    let url = player.url(&upstream);
+    let query = ChromeCRXQuery { endpoint: &url, ..query };
+    truncate();
+    assert_eq!(downloader.download_resume_blocking(&query, &path).unwrap().status, 206);
+    assert_eq!(std::fs::read(&path).unwrap(), crx);
+
+    for x in [cassette, crx_dl::validators_path(&path), path] {
+        std::fs::remove_file(x).unwrap();
+    }
+}
+
+#[test]
+fn never_replaces_a_file_with_nothing() {
+    let (server, _, id) = serve(|_| MockResponse::NoContent);
+    let url = server.url();
+    let path = std::env::temp_dir().join(format!("crx-dl-empty-{}.crx", std::process::id()));
+    std::fs::write(&path, b"cached").unwrap();
+
+    // A 204 that isn't looked into, and a 200 without a body
+    let query = ChromeCRXQuery { endpoint: &url, x: &id, response: "crx", ..Default::default() };
+    assert!(matches!(Downloader::default().download_to_path_blocking(&query, &path), Err(Error::EmptyResponse(_))));
+    server.serve(&id, MockResponse::Status(200));
+    let query = ChromeCRXQuery { verify_id: false, ..query };
+    assert!(matches!(Downloader::default().download_to_path_blocking(&query, &path), Err(Error::EmptyResponse(_))));
+    assert_eq!(std::fs::read(&path).unwrap(), b"cached");
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn checks_opera_responses() {
+    let (server, crx, slug) = serve(|crx| MockResponse::Crx { version: "1.0".into(), crx });
+    let root = server.root();
+    let query = OperaCRXQuery { endpoint: &root, ..OperaCRXQuery::new(&slug) };
+    assert_eq!(query.download_blocking().unwrap(), crx);
+
+    server.serve(&slug, MockResponse::Status(200));
+    assert!(matches!(query.download_blocking(), Err(Error::EmptyResponse(status)) if status == 200));
+    server.serve(&slug, MockResponse::NoContent);
+    assert!(matches!(query.download_blocking(), Err(Error::EmptyResponse(status)) if status == 204));
+    server.serve(&slug, MockResponse::Status(503));
+    assert!(matches!(query.download_blocking(), Err(Error::Status(status)) if status == 503));
+}